@@ -0,0 +1,214 @@
+//! Speedup and memory ratio against a baseline app.
+//!
+//! For each dataset, analysis, and machine, every app is compared
+//! with the median of the baseline app. The speedup is the baseline
+//! time over the app time, and the memory ratio is the app memory
+//! over the baseline memory. The confidence intervals are bootstrapped.
+use std::io::Write;
+
+use crate::row::{format_optional, BenchRow};
+use crate::stats::{self, SplitMix64};
+use crate::summary::group_rows;
+
+const CI_LEVEL: f64 = 0.95;
+const SEED: u64 = 0x5E6B_E4C4;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Ratio {
+    pub estimate: f64,
+    /// 95% bootstrap confidence interval.
+    pub ci: Option<(f64, f64)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub app: String,
+    pub version: String,
+    pub dataset: String,
+    pub analysis: String,
+    pub os: String,
+    pub cpu: String,
+    pub baseline: String,
+    pub speedup: Option<Ratio>,
+    pub memory_ratio: Option<Ratio>,
+}
+
+/// Compares every app with the `baseline` app (case-insensitive).
+/// Groups without baseline replicates are skipped.
+pub fn compare_to_baseline(
+    rows: &[BenchRow],
+    baseline: &str,
+    exclude_outliers: bool,
+) -> Vec<Comparison> {
+    let groups = group_rows(rows, exclude_outliers);
+    let is_baseline = |row: &BenchRow| row.app.eq_ignore_ascii_case(baseline);
+    let same_block = |a: &BenchRow, b: &BenchRow| {
+        a.dataset == b.dataset && a.analysis == b.analysis && a.machine() == b.machine()
+    };
+    let mut comparisons = Vec::new();
+    for group in groups.iter().filter(|g| !is_baseline(g[0])) {
+        let first = group[0];
+        let base: Vec<&BenchRow> = groups
+            .iter()
+            .filter(|g| is_baseline(g[0]) && same_block(g[0], first))
+            .flatten()
+            .copied()
+            .collect();
+        if base.is_empty() {
+            continue;
+        }
+        // Seeded per group, so adding other apps or files
+        // does not change the intervals of this one.
+        let mut rng = SplitMix64::new(SEED ^ group_hash(first));
+        let exec_times =
+            |rows: &[&BenchRow]| -> Vec<f64> { rows.iter().map(|r| r.exec_time_secs).collect() };
+        let mem_usages = |rows: &[&BenchRow]| -> Vec<f64> {
+            rows.iter()
+                .filter_map(|r| r.ram_usage_mb.map(f64::from))
+                .collect()
+        };
+        comparisons.push(Comparison {
+            app: first.app.clone(),
+            version: first.version.clone(),
+            dataset: first.dataset.clone(),
+            analysis: first.analysis.clone(),
+            os: first.os.clone(),
+            cpu: first.cpu.clone(),
+            baseline: base[0].app.clone(),
+            speedup: ratio(&exec_times(&base), &exec_times(group), &mut rng),
+            memory_ratio: ratio(&mem_usages(group), &mem_usages(&base), &mut rng),
+        });
+    }
+    comparisons
+}
+
+// FNV-1a hash of the group key, stable across runs and Rust versions.
+fn group_hash(row: &BenchRow) -> u64 {
+    let machine = row.machine();
+    let key = [
+        row.app.as_str(),
+        row.version.as_str(),
+        row.dataset.as_str(),
+        row.analysis.as_str(),
+        machine.as_str(),
+    ];
+    key.iter()
+        .flat_map(|k| k.bytes().chain([0]))
+        .fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+// Ratio of the medians of `a` over `b`.
+fn ratio(a: &[f64], b: &[f64], rng: &mut SplitMix64) -> Option<Ratio> {
+    let median_ratio = |a: &[f64], b: &[f64]| Some(stats::median(a)? / stats::median(b)?);
+    let estimate = median_ratio(a, b).filter(|r| r.is_finite())?;
+    Some(Ratio {
+        estimate,
+        ci: stats::bootstrap_ci(a, b, median_ratio, CI_LEVEL, rng),
+    })
+}
+
+pub(crate) fn write_comparisons<W: Write>(
+    writer: &mut W,
+    comparisons: &[Comparison],
+) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record([
+        "Apps",
+        "Version",
+        "Datasets",
+        "Analyses",
+        "OS_name",
+        "CPU",
+        "Baseline",
+        "Speedup",
+        "Speedup_ci_lower",
+        "Speedup_ci_upper",
+        "Memory_ratio",
+        "Memory_ratio_ci_lower",
+        "Memory_ratio_ci_upper",
+    ])?;
+    for c in comparisons {
+        let mut record = vec![
+            c.app.clone(),
+            c.version.clone(),
+            c.dataset.clone(),
+            c.analysis.clone(),
+            c.os.clone(),
+            c.cpu.clone(),
+            c.baseline.clone(),
+        ];
+        record.extend(ratio_fields(c.speedup));
+        record.extend(ratio_fields(c.memory_ratio));
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn ratio_fields(ratio: Option<Ratio>) -> [String; 3] {
+    [
+        format_optional(ratio.map(|r| r.estimate)),
+        format_optional(ratio.and_then(|r| r.ci).map(|ci| ci.0)),
+        format_optional(ratio.and_then(|r| r.ci).map(|ci| ci.1)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::row::RowBuilder;
+
+    #[test]
+    fn test_compare_to_baseline() {
+        let rows = [
+            RowBuilder::new("AMAS", "Wu", 10.0).ram_mb(400.0).build(),
+            RowBuilder::new("AMAS", "Wu", 12.0).ram_mb(400.0).build(),
+            RowBuilder::new("SEGUL CLI", "Wu", 2.0)
+                .ram_mb(100.0)
+                .build(),
+            RowBuilder::new("SEGUL CLI", "Wu", 2.2)
+                .ram_mb(100.0)
+                .build(),
+            RowBuilder::new("Phyluce", "Wu", 30.0).ram_mb(800.0).build(),
+        ];
+        let comparisons = compare_to_baseline(&rows, "amas", false);
+        assert_eq!(2, comparisons.len());
+        let segul = &comparisons[0];
+        assert_eq!("AMAS", segul.baseline);
+        assert!((segul.speedup.unwrap().estimate - 5.238095).abs() < 1e-6);
+        assert_eq!(0.25, segul.memory_ratio.unwrap().estimate);
+        let (lower, upper) = segul.speedup.unwrap().ci.unwrap();
+        assert!(lower <= 5.238095 && upper >= 5.238095);
+    }
+
+    #[test]
+    fn test_ci_independent_of_other_groups() {
+        let rows = [
+            RowBuilder::new("AMAS", "Wu", 10.0).ram_mb(400.0).build(),
+            RowBuilder::new("AMAS", "Wu", 12.0).ram_mb(420.0).build(),
+            RowBuilder::new("AMAS", "Wu", 11.0).ram_mb(410.0).build(),
+            RowBuilder::new("SEGUL CLI", "Wu", 2.0)
+                .ram_mb(100.0)
+                .build(),
+            RowBuilder::new("SEGUL CLI", "Wu", 2.2)
+                .ram_mb(110.0)
+                .build(),
+            RowBuilder::new("SEGUL CLI", "Wu", 2.5)
+                .ram_mb(105.0)
+                .build(),
+        ];
+        let mut more_rows = vec![
+            RowBuilder::new("Phyluce", "Wu", 30.0).ram_mb(800.0).build(),
+            RowBuilder::new("Phyluce", "Wu", 31.0).ram_mb(790.0).build(),
+        ];
+        more_rows.extend(rows.iter().cloned());
+        let segul = |rows: &[BenchRow]| {
+            let comparisons = compare_to_baseline(rows, "AMAS", false);
+            let segul = comparisons.iter().find(|c| c.app == "SEGUL CLI").unwrap();
+            (segul.speedup.unwrap().ci, segul.memory_ratio.unwrap().ci)
+        };
+        assert_eq!(segul(&rows), segul(&more_rows));
+    }
+}
@@ -0,0 +1,104 @@
+//! Result lines written by the BSD/macOS `/usr/bin/time -l`.
+//!
+//! Each replicate starts with a `real user sys` line, followed by
+//! one `<value>  <label>` line per resource usage counter.
+//! The values are converted into the same units as the GNU output.
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::error::ErrorReason;
+use crate::parser::format_elapsed;
+use crate::types::BenchmarkResult;
+
+lazy_static! {
+    static ref TIME_LINE: Regex = Regex::new(
+        r"^\s*(?P<real>\d+\.\d+)\s+real\s+(?P<user>\d+\.\d+)\s+user\s+(?P<sys>\d+\.\d+)\s+sys\s*$"
+    )
+    .expect("Failed to compile regex");
+}
+
+pub(crate) fn is_time_line(line: &str) -> bool {
+    TIME_LINE.is_match(line)
+}
+
+pub(crate) fn parse_time_line(line: &str) -> Result<BenchmarkResult, ErrorReason> {
+    let caps = TIME_LINE.captures(line).ok_or(ErrorReason::InvalidTime)?;
+    let parse = |name: &str| {
+        caps[name]
+            .parse::<f64>()
+            .map_err(|_| ErrorReason::InvalidTime)
+    };
+    let real = parse("real")?;
+    let user = parse("user")?;
+    let sys = parse("sys")?;
+    let mut bench = BenchmarkResult::new();
+    bench.exec_time = format_elapsed(real);
+    bench.cpu_usage = format!("{:.0}%", cpu_percent(real, user + sys));
+    bench.user_time = Some(user);
+    bench.system_time = Some(sys);
+    Ok(bench)
+}
+
+pub(crate) fn parse_resource_line(
+    bench: &mut BenchmarkResult,
+    line: &str,
+) -> Result<(), ErrorReason> {
+    let line = line.trim();
+    let (value, label) =
+        line.split_once(char::is_whitespace)
+            .ok_or(ErrorReason::MissingColumns {
+                expected: 2,
+                found: 1,
+            })?;
+    let value = value
+        .parse::<u64>()
+        .map_err(|_| ErrorReason::InvalidCount)?;
+    match label.trim() {
+        "maximum resident set size" => bench.mem_usage = bytes_to_kb(value).to_string(),
+        "page reclaims" => bench.minor_page_faults = Some(value),
+        "page faults" => bench.major_page_faults = Some(value),
+        "block input operations" => bench.fs_inputs = Some(value),
+        "block output operations" => bench.fs_outputs = Some(value),
+        "voluntary context switches" => bench.voluntary_switches = Some(value),
+        "involuntary context switches" => bench.involuntary_switches = Some(value),
+        "instructions retired" => bench.instructions_retired = Some(value),
+        "peak memory footprint" => bench.peak_memory_kb = Some(bytes_to_kb(value)),
+        _ => (),
+    }
+    Ok(())
+}
+
+fn bytes_to_kb(bytes: u64) -> u64 {
+    bytes / 1024
+}
+
+fn cpu_percent(real: f64, cpu_time: f64) -> f64 {
+    if real > 0.0 {
+        cpu_time / real * 100.0
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bsd_result() {
+        let mut bench =
+            parse_time_line("        3.60 real         4.50 user         0.90 sys").unwrap();
+        parse_resource_line(
+            &mut bench,
+            "           618246144  maximum resident set size",
+        )
+        .unwrap();
+        parse_resource_line(&mut bench, "               37080  page reclaims").unwrap();
+        parse_resource_line(&mut bench, "           598654976  peak memory footprint").unwrap();
+        assert_eq!("0:03.60", bench.exec_time);
+        assert_eq!("150%", bench.cpu_usage);
+        assert_eq!("603756", bench.mem_usage);
+        assert_eq!(Some(37080), bench.minor_page_faults);
+        assert_eq!(Some(584624), bench.peak_memory_kb);
+    }
+}
@@ -1,17 +1,177 @@
 use std::{
     ffi::OsStr,
     path::{Path, PathBuf},
+    process,
 };
 
+use bench_parser::{
+    AppSelector, InputFormat, OutlierMethod, OutputFormat, TestMethod, WarmupOutput,
+};
 use clap::{crate_description, crate_name, crate_version, Arg, ArgMatches, Command};
 use glob::glob;
 
 pub fn parser_arg() -> ArgMatches {
-    Command::new(crate_name!())
+    let command = Command::new(crate_name!())
         .version(crate_version!())
         .about(crate_description!())
         .author("Heru Handika")
         .arg_required_else_help(true)
+        .args_conflicts_with_subcommands(true);
+    input_args(command, "result")
+        .arg(
+            Arg::new("summarize")
+                .long("summarize")
+                .help("Write the summary statistics of each dataset")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::new("output-format")
+                .long("output-format")
+                .help("Write the rows as CSV, TSV, nested JSON, NDJSON, Parquet, or Arrow IPC")
+                .possible_values(["csv", "tsv", "json", "ndjson", "parquet", "arrow"])
+                .default_value("csv")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("sqlite")
+                .long("sqlite")
+                .help("Also write the rows into a SQLite database. Re-parsed rows are updated")
+                .takes_value(true)
+                .value_name("DB"),
+        )
+        .arg(
+            Arg::new("scaling")
+                .long("scaling")
+                .help("Fit how the time and memory usage of each app scale with the dataset size")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::new("warmup-output")
+                .long("warmup-output")
+                .help("Drop the warm-up replicates, write them to a separate file, or flag them")
+                .possible_values(["drop", "file", "flag"])
+                .default_value("drop")
+                .requires("discard-warmup")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("baseline")
+                .long("baseline")
+                .help("Compare the speed and memory usage of every app with this app")
+                .takes_value(true)
+                .value_name("APP"),
+        )
+        .subcommand(compare_command())
+        .subcommand(diff_command())
+        .try_get_matches()
+        .unwrap_or_else(|e| {
+            // Exit code 2 means skipped datasets, so bad arguments exit with 1.
+            if e.use_stderr() {
+                e.print().expect("Failed printing the usage error");
+                process::exit(1);
+            }
+            e.exit()
+        })
+}
+
+fn compare_command() -> Command<'static> {
+    let command = Command::new("compare")
+        .about("Test the difference between two apps or two versions of an app")
+        .arg_required_else_help(true);
+    input_args(command, "comparison")
+        .arg(
+            Arg::new("first")
+                .long("first")
+                .help("First app to compare")
+                .required(true)
+                .takes_value(true)
+                .value_name("APP"),
+        )
+        .arg(
+            Arg::new("first-version")
+                .long("first-version")
+                .help("Only use this version of the first app")
+                .takes_value(true)
+                .value_name("VERSION"),
+        )
+        .arg(
+            Arg::new("second")
+                .long("second")
+                .help("Second app to compare. Defaults to the first app, with a different version")
+                .takes_value(true)
+                .value_name("APP"),
+        )
+        .arg(
+            Arg::new("second-version")
+                .long("second-version")
+                .help("Only use this version of the second app")
+                .takes_value(true)
+                .value_name("VERSION"),
+        )
+        .arg(
+            Arg::new("test")
+                .long("test")
+                .help("Significance test")
+                .possible_values(["mann-whitney", "welch"])
+                .default_value("mann-whitney")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("markdown")
+                .long("markdown")
+                .help("Write the table in Markdown instead of CSV")
+                .takes_value(false),
+        )
+}
+
+fn diff_command() -> Command<'static> {
+    Command::new("diff")
+        .about("Find regressions between two parsed result sets")
+        .arg_required_else_help(true)
+        .arg(
+            Arg::new("old")
+                .long("old")
+                .help("Old results in CSV")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("new")
+                .long("new")
+                .help("New results in CSV")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .help("Output file path")
+                .default_value("diff")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("threshold")
+                .long("threshold")
+                .help(
+                    "Increase in the median time or peak RSS, in percent, counted as a regression",
+                )
+                .default_value("5")
+                .takes_value(true)
+                .value_name("PERCENT")
+                .validator(|v| v.parse::<f64>()),
+        )
+        .arg(
+            Arg::new("exclude-outliers")
+                .long("exclude-outliers")
+                .help("Leave the rows flagged as outliers out of the medians")
+                .takes_value(false),
+        )
+}
+
+// Arguments to read the benchmark files, shared by every command.
+fn input_args<'a>(command: Command<'a>, output: &'a str) -> Command<'a> {
+    command
         .arg(
             Arg::new("input")
                 .short('i')
@@ -25,18 +185,72 @@ pub fn parser_arg() -> ArgMatches {
                 .short('o')
                 .long("output")
                 .help("Output file path")
-                .default_value("result")
+                .default_value(output)
                 .takes_value(true),
         )
         .arg(
-            Arg::new("size")
-                .short('s')
-                .long("size")
-                .help("Dataset size")
-                .default_value("5")
+            Arg::new("expect-replicates")
+                .long("expect-replicates")
+                .short_alias('s')
+                .alias("size")
+                .help("Expected number of replicates in each dataset")
+                .takes_value(true)
+                .validator(|v| v.parse::<usize>()),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .help("Input format. Detected for each file by default")
+                .possible_values(["auto", "gnu", "gnu-verbose", "bsd", "hyperfine"])
+                .default_value("auto")
                 .takes_value(true),
         )
-        .get_matches()
+        .arg(
+            Arg::new("datasets")
+                .long("datasets")
+                .help("Dataset registry in TOML. Uses the built-in registry by default")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("apps")
+                .long("apps")
+                .help("App registry in TOML. Uses the built-in registry by default")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("analyses")
+                .long("analyses")
+                .help("Analysis names in TOML. Uses the built-in names by default")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("lenient")
+                .long("lenient")
+                .help("Skip malformed datasets and write a warnings report")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::new("discard-warmup")
+                .long("discard-warmup")
+                .help("Discard the first N replicates of each dataset")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|v| v.parse::<usize>()),
+        )
+        .arg(
+            Arg::new("outliers")
+                .long("outliers")
+                .help("Flag the outliers of each dataset by the IQR or the MAD")
+                .possible_values(["iqr", "mad"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("exclude-outliers")
+                .long("exclude-outliers")
+                .help("Leave the outliers out of the statistics")
+                .requires("outliers")
+                .takes_value(false),
+        )
 }
 
 pub fn parse_input(matches: &ArgMatches) -> Vec<PathBuf> {
@@ -70,10 +284,127 @@ pub fn parse_output(matches: &ArgMatches) -> &Path {
     Path::new(matches.value_of("output").expect("No output provided"))
 }
 
-pub fn parse_dataset_size(matches: &ArgMatches) -> usize {
+pub fn parse_expected_replicates(matches: &ArgMatches) -> Option<usize> {
+    matches.value_of("expect-replicates").map(|size| {
+        size.parse::<usize>()
+            .expect("Failed parsing expected replicates")
+    })
+}
+
+pub fn parse_format(matches: &ArgMatches) -> Option<InputFormat> {
+    match matches
+        .value_of("format")
+        .expect("No input format provided")
+    {
+        "auto" => None,
+        format => Some(format.parse().expect("Failed parsing input format")),
+    }
+}
+
+pub fn parse_datasets(matches: &ArgMatches) -> Option<&Path> {
+    matches.value_of("datasets").map(Path::new)
+}
+
+pub fn parse_apps(matches: &ArgMatches) -> Option<&Path> {
+    matches.value_of("apps").map(Path::new)
+}
+
+pub fn parse_analyses(matches: &ArgMatches) -> Option<&Path> {
+    matches.value_of("analyses").map(Path::new)
+}
+
+pub fn parse_lenient(matches: &ArgMatches) -> bool {
+    matches.is_present("lenient")
+}
+
+pub fn parse_summarize(matches: &ArgMatches) -> bool {
+    matches.is_present("summarize")
+}
+
+pub fn parse_output_format(matches: &ArgMatches) -> OutputFormat {
+    matches
+        .value_of("output-format")
+        .expect("No output format provided")
+        .parse()
+        .expect("Failed parsing output format")
+}
+
+pub fn parse_sqlite(matches: &ArgMatches) -> Option<&Path> {
+    matches.value_of("sqlite").map(Path::new)
+}
+
+pub fn parse_scaling(matches: &ArgMatches) -> bool {
+    matches.is_present("scaling")
+}
+
+pub fn parse_discard_warmup(matches: &ArgMatches) -> Option<usize> {
+    matches.value_of("discard-warmup").map(|n| {
+        n.parse::<usize>()
+            .expect("Failed parsing warm-up replicates")
+    })
+}
+
+pub fn parse_warmup_output(matches: &ArgMatches) -> WarmupOutput {
+    matches
+        .value_of("warmup-output")
+        .expect("No warm-up output provided")
+        .parse()
+        .expect("Failed parsing warm-up output")
+}
+
+pub fn parse_outliers(matches: &ArgMatches) -> Option<OutlierMethod> {
+    matches
+        .value_of("outliers")
+        .map(|method| method.parse().expect("Failed parsing outlier method"))
+}
+
+pub fn parse_exclude_outliers(matches: &ArgMatches) -> bool {
+    matches.is_present("exclude-outliers")
+}
+
+pub fn parse_baseline(matches: &ArgMatches) -> Option<&str> {
+    matches.value_of("baseline")
+}
+
+pub fn parse_first(matches: &ArgMatches) -> AppSelector {
+    AppSelector::new(
+        matches.value_of("first").expect("No first app provided"),
+        matches.value_of("first-version"),
+    )
+}
+
+pub fn parse_second(matches: &ArgMatches) -> AppSelector {
+    let app = matches
+        .value_of("second")
+        .or_else(|| matches.value_of("first"))
+        .expect("No second app provided");
+    AppSelector::new(app, matches.value_of("second-version"))
+}
+
+pub fn parse_test(matches: &ArgMatches) -> TestMethod {
+    matches
+        .value_of("test")
+        .expect("No test provided")
+        .parse()
+        .expect("Failed parsing test method")
+}
+
+pub fn parse_markdown(matches: &ArgMatches) -> bool {
+    matches.is_present("markdown")
+}
+
+pub fn parse_old(matches: &ArgMatches) -> &Path {
+    Path::new(matches.value_of("old").expect("No old results provided"))
+}
+
+pub fn parse_new(matches: &ArgMatches) -> &Path {
+    Path::new(matches.value_of("new").expect("No new results provided"))
+}
+
+pub fn parse_threshold(matches: &ArgMatches) -> f64 {
     matches
-        .value_of("size")
-        .expect("No dataset size provided")
-        .parse::<usize>()
-        .expect("Failed parsing dataset size")
+        .value_of("threshold")
+        .expect("No threshold provided")
+        .parse()
+        .expect("Failed parsing threshold")
 }
@@ -0,0 +1,268 @@
+//! Parquet and Arrow IPC output.
+//!
+//! The columns are the CSV columns, with typed values: counts and
+//! memory usage in kB are unsigned integers, times and percentages are
+//! floats, benchmark dates are dates, flags are booleans, and missing
+//! values are nulls. The rows are written in record batches.
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, BooleanArray, Date32Array, Float32Array, Float64Array, Int32Array, RecordBatch,
+    StringArray, UInt64Array,
+};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{Field, Schema, SchemaRef};
+use chrono::NaiveDate;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use crate::error::BenchParseError;
+use crate::row::{BenchRow, HEADER};
+
+/// Rows in a record batch, so only this many rows are
+/// converted into columns at a time.
+pub(crate) const BATCH_SIZE: usize = 8192;
+const ROW_GROUP_SIZE: usize = 8 * BATCH_SIZE;
+
+pub(crate) enum ColumnarWriter<W: Write + Send> {
+    Parquet(ArrowWriter<W>),
+    Arrow(FileWriter<W>),
+}
+
+impl<W: Write + Send> ColumnarWriter<W> {
+    pub(crate) fn parquet(writer: W) -> Result<Self, BenchParseError> {
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_size(ROW_GROUP_SIZE)
+            .build();
+        Ok(Self::Parquet(ArrowWriter::try_new(
+            writer,
+            schema()?,
+            Some(props),
+        )?))
+    }
+
+    pub(crate) fn arrow(writer: W) -> Result<Self, BenchParseError> {
+        Ok(Self::Arrow(FileWriter::try_new(
+            writer,
+            schema()?.as_ref(),
+        )?))
+    }
+
+    pub(crate) fn write(&mut self, rows: &[&BenchRow]) -> Result<(), BenchParseError> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let batch = record_batch(rows)?;
+        match self {
+            ColumnarWriter::Parquet(writer) => writer.write(&batch)?,
+            ColumnarWriter::Arrow(writer) => writer.write(&batch)?,
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(self) -> Result<(), BenchParseError> {
+        match self {
+            ColumnarWriter::Parquet(writer) => writer.into_inner()?.flush()?,
+            ColumnarWriter::Arrow(writer) => writer.into_inner()?.flush()?,
+        }
+        Ok(())
+    }
+}
+
+fn schema() -> Result<SchemaRef, BenchParseError> {
+    Ok(record_batch(&[])?.schema())
+}
+
+/// Rows as one record batch. The schema does not depend on the rows.
+fn record_batch(rows: &[&BenchRow]) -> Result<RecordBatch, BenchParseError> {
+    // Columns with whether they can have nulls.
+    let text = |value: fn(&BenchRow) -> &str| -> (ArrayRef, bool) {
+        let array = StringArray::from_iter_values(rows.iter().copied().map(value));
+        (Arc::new(array), false)
+    };
+    let count = |value: fn(&BenchRow) -> usize| -> (ArrayRef, bool) {
+        let array = UInt64Array::from_iter_values(rows.iter().copied().map(|r| value(r) as u64));
+        (Arc::new(array), false)
+    };
+    let optional_count = |value: fn(&BenchRow) -> Option<u64>| -> (ArrayRef, bool) {
+        (
+            Arc::new(UInt64Array::from_iter(rows.iter().copied().map(value))),
+            true,
+        )
+    };
+    let optional_float = |value: fn(&BenchRow) -> Option<f64>| -> (ArrayRef, bool) {
+        (
+            Arc::new(Float64Array::from_iter(rows.iter().copied().map(value))),
+            true,
+        )
+    };
+    let flag = |value: fn(&BenchRow) -> bool| -> (ArrayRef, bool) {
+        let array = BooleanArray::from_iter(rows.iter().copied().map(|r| Some(value(r))));
+        (Arc::new(array), false)
+    };
+    // In the order of the CSV columns.
+    let columns = [
+        text(|r| &r.app),
+        text(|r| &r.version),
+        text(|r| &r.pubs),
+        text(|r| &r.dataset),
+        count(|r| r.ntax),
+        count(|r| r.char_counts),
+        count(|r| r.aln_counts),
+        count(|r| r.site_counts),
+        text(|r| &r.datatype),
+        text(|r| &r.analysis),
+        text(|r| &r.platform),
+        text(|r| &r.app_type),
+        text(|r| &r.os),
+        text(|r| &r.cpu),
+        (
+            Arc::new(Date32Array::from_iter(
+                rows.iter().copied().map(|r| date(&r.date)),
+            )),
+            true,
+        ),
+        flag(|r| r.latest_bench),
+        text(|r| &r.exec_time),
+        optional_count(|r| r.ram_usage_kb),
+        optional_float(|r| r.cpu_usage),
+        (
+            Arc::new(Float64Array::from_iter_values(
+                rows.iter().copied().map(|r| r.exec_time_secs),
+            )),
+            false,
+        ),
+        (
+            Arc::new(Float32Array::from_iter(
+                rows.iter().copied().map(|r| r.ram_usage_mb),
+            )),
+            true,
+        ),
+        count(|r| r.replicates),
+        optional_float(|r| r.user_time_secs),
+        optional_float(|r| r.system_time_secs),
+        optional_count(|r| r.major_page_faults),
+        optional_count(|r| r.minor_page_faults),
+        optional_count(|r| r.voluntary_switches),
+        optional_count(|r| r.involuntary_switches),
+        optional_count(|r| r.fs_inputs),
+        optional_count(|r| r.fs_outputs),
+        (
+            Arc::new(Int32Array::from_iter(
+                rows.iter().copied().map(|r| r.exit_status),
+            )),
+            true,
+        ),
+        optional_count(|r| r.instructions_retired),
+        optional_count(|r| r.peak_memory_kb),
+        optional_count(|r| r.cores.map(|c| c as u64)),
+        optional_count(|r| r.threads.map(|t| t as u64)),
+        optional_count(|r| r.total_ram_kb),
+        text(|r| &r.kernel),
+        optional_float(|r| r.cpu_usage_normalized),
+        flag(|r| r.warmup),
+        flag(|r| r.outlier),
+        optional_float(|r| r.mbases_per_sec()),
+        optional_float(|r| r.alignments_per_sec()),
+        optional_float(|r| r.ram_usage_mb_per_mbase()),
+        optional_float(|r| r.secs_per_1k_alignments()),
+    ];
+    let fields: Vec<Field> = HEADER
+        .iter()
+        .zip(&columns)
+        .map(|(name, (array, nullable))| Field::new(name, array.data_type().clone(), *nullable))
+        .collect();
+    let arrays = columns.into_iter().map(|(array, _)| array).collect();
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?)
+}
+
+// Days since the epoch of a `MM/DD/YYYY` benchmark date.
+fn date(date: &str) -> Option<i32> {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?;
+    let date = NaiveDate::parse_from_str(date, "%m/%d/%Y").ok()?;
+    i32::try_from((date - epoch).num_days()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    use arrow_array::Array;
+    use arrow_ipc::reader::FileReader;
+    use arrow_schema::DataType;
+    use bytes::Bytes;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use crate::row::RowBuilder;
+
+    #[test]
+    fn test_record_batch() {
+        let row = RowBuilder::new("SEGUL CLI", "Wu", 2.61)
+            .ram_kb(350760)
+            .build();
+        let batch = record_batch(&[&row, &row]).unwrap();
+        let schema = batch.schema();
+        assert_eq!(HEADER.len(), schema.fields().len());
+        assert_eq!(2, batch.num_rows());
+        for (field, name) in schema.fields().iter().zip(HEADER.iter()) {
+            assert_eq!(name, field.name());
+        }
+        let field = |name: &str| schema.field_with_name(name).unwrap().clone();
+        assert_eq!(&DataType::Float64, field("Execution_time_secs").data_type());
+        assert_eq!(&DataType::UInt64, field("RAM_usage_kb").data_type());
+        assert!(field("RAM_usage_kb").is_nullable());
+        assert_eq!(&DataType::Boolean, field("Warmup").data_type());
+        assert_eq!(&DataType::Date32, field("Benchmark_dates").data_type());
+        assert!(!field("Apps").is_nullable());
+    }
+
+    fn write(mut writer: ColumnarWriter<&mut Vec<u8>>, rows: &[&BenchRow]) {
+        for chunk in rows.chunks(BATCH_SIZE) {
+            writer.write(chunk).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_write_parquet() {
+        let row = RowBuilder::new("SEGUL CLI", "Wu", 2.61).build();
+        let mut output = Vec::new();
+        write(
+            ColumnarWriter::parquet(&mut output).unwrap(),
+            &vec![&row; ROW_GROUP_SIZE + 1],
+        );
+        let builder = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(output)).unwrap();
+        assert_eq!(2, builder.metadata().num_row_groups());
+        assert_eq!(schema().unwrap(), *builder.schema());
+        let batches: Vec<RecordBatch> = builder.build().unwrap().map(|b| b.unwrap()).collect();
+        let num_rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+        assert_eq!(ROW_GROUP_SIZE + 1, num_rows);
+    }
+
+    #[test]
+    fn test_write_arrow() {
+        let row = RowBuilder::new("SEGUL CLI", "Wu", 2.61)
+            .date("10/04/2022")
+            .build();
+        let mut output = Vec::new();
+        write(
+            ColumnarWriter::arrow(&mut output).unwrap(),
+            &[&row, &row, &row],
+        );
+        let reader = FileReader::try_new(Cursor::new(output), None).unwrap();
+        assert_eq!(schema().unwrap(), reader.schema());
+        let batch = reader.map(|b| b.unwrap()).next().unwrap();
+        assert_eq!(3, batch.num_rows());
+        let dates = batch
+            .column_by_name("Benchmark_dates")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Date32Array>()
+            .unwrap();
+        assert_eq!(Some(19269), dates.iter().next().unwrap());
+    }
+}
@@ -0,0 +1,330 @@
+//! Significance tests between two apps or two versions of an app.
+//!
+//! The replicates of each dataset, analysis, and machine are tested
+//! separately for the execution time and the memory usage. The p-values
+//! of each metric are adjusted with the Holm method across the groups.
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::row::{format_optional, BenchRow};
+use crate::stats::{self, TestResult};
+use crate::summary::group_rows;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestMethod {
+    MannWhitney,
+    Welch,
+}
+
+impl TestMethod {
+    fn test(&self, a: &[f64], b: &[f64]) -> Option<TestResult> {
+        match self {
+            TestMethod::MannWhitney => stats::mann_whitney_u(a, b),
+            TestMethod::Welch => stats::welch_t(a, b),
+        }
+    }
+}
+
+impl fmt::Display for TestMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TestMethod::MannWhitney => write!(f, "mann-whitney"),
+            TestMethod::Welch => write!(f, "welch"),
+        }
+    }
+}
+
+impl FromStr for TestMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mann-whitney" => Ok(TestMethod::MannWhitney),
+            "welch" => Ok(TestMethod::Welch),
+            _ => Err(format!("Unknown test method: {}", s)),
+        }
+    }
+}
+
+/// Selects the rows of an app, and optionally of one version.
+/// The app name is case-insensitive.
+#[derive(Debug, Clone)]
+pub struct AppSelector {
+    pub app: String,
+    pub version: Option<String>,
+}
+
+impl AppSelector {
+    pub fn new(app: &str, version: Option<&str>) -> Self {
+        Self {
+            app: app.to_string(),
+            version: version.map(|v| v.to_string()),
+        }
+    }
+
+    /// Whether a row can match both selectors, e.g. the same app
+    /// without a version for either.
+    pub fn overlaps(&self, other: &AppSelector) -> bool {
+        self.app.eq_ignore_ascii_case(&other.app)
+            && match (&self.version, &other.version) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            }
+    }
+
+    fn matches(&self, row: &BenchRow) -> bool {
+        row.app.eq_ignore_ascii_case(&self.app)
+            && self.version.as_ref().is_none_or(|v| *v == row.version)
+    }
+}
+
+impl fmt::Display for AppSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{} {}", self.app, version),
+            None => write!(f, "{}", self.app),
+        }
+    }
+}
+
+/// Test of a metric. The p-value is adjusted across the groups.
+#[derive(Debug, Clone, Copy)]
+pub struct MetricTest {
+    pub median_first: Option<f64>,
+    pub median_second: Option<f64>,
+    pub result: Option<TestResult>,
+    pub p_adjusted: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupTest {
+    pub dataset: String,
+    pub analysis: String,
+    pub os: String,
+    pub cpu: String,
+    pub n_first: usize,
+    pub n_second: usize,
+    pub exec_time: MetricTest,
+    pub mem_usage: MetricTest,
+}
+
+/// Tests the replicates of `first` against `second` for every dataset,
+/// analysis, and machine that has both. Rows that match both selectors
+/// are left out of both samples.
+pub fn compare_apps(
+    rows: &[BenchRow],
+    first: &AppSelector,
+    second: &AppSelector,
+    method: TestMethod,
+    exclude_outliers: bool,
+) -> Vec<GroupTest> {
+    let groups = group_rows(rows, exclude_outliers);
+    let mut tests = Vec::new();
+    // Each app and version is its own group, so a block has one group
+    // per version of the selected app.
+    let mut blocks: Vec<(&BenchRow, Vec<&BenchRow>, Vec<&BenchRow>)> = Vec::new();
+    for group in &groups {
+        let row = group[0];
+        let is_first = first.matches(row);
+        let is_second = second.matches(row);
+        if is_first == is_second {
+            continue;
+        }
+        let block = match blocks.iter().position(|(b, _, _)| same_block(b, row)) {
+            Some(i) => &mut blocks[i],
+            None => {
+                blocks.push((row, Vec::new(), Vec::new()));
+                blocks.last_mut().expect("Empty blocks")
+            }
+        };
+        if is_first {
+            block.1.extend(group);
+        } else {
+            block.2.extend(group);
+        }
+    }
+    for (row, a, b) in blocks
+        .iter()
+        .filter(|(_, a, b)| !a.is_empty() && !b.is_empty())
+    {
+        let exec_times =
+            |rows: &[&BenchRow]| -> Vec<f64> { rows.iter().map(|r| r.exec_time_secs).collect() };
+        let mem_usages = |rows: &[&BenchRow]| -> Vec<f64> {
+            rows.iter()
+                .filter_map(|r| r.ram_usage_mb.map(f64::from))
+                .collect()
+        };
+        tests.push(GroupTest {
+            dataset: row.dataset.clone(),
+            analysis: row.analysis.clone(),
+            os: row.os.clone(),
+            cpu: row.cpu.clone(),
+            n_first: a.len(),
+            n_second: b.len(),
+            exec_time: test_metric(&exec_times(a), &exec_times(b), method),
+            mem_usage: test_metric(&mem_usages(a), &mem_usages(b), method),
+        });
+    }
+    adjust(&mut tests, |t| &mut t.exec_time);
+    adjust(&mut tests, |t| &mut t.mem_usage);
+    tests
+}
+
+fn same_block(a: &BenchRow, b: &BenchRow) -> bool {
+    a.dataset == b.dataset && a.analysis == b.analysis && a.machine() == b.machine()
+}
+
+fn test_metric(a: &[f64], b: &[f64], method: TestMethod) -> MetricTest {
+    MetricTest {
+        median_first: stats::median(a),
+        median_second: stats::median(b),
+        result: method.test(a, b),
+        p_adjusted: None,
+    }
+}
+
+fn adjust<F>(tests: &mut [GroupTest], metric: F)
+where
+    F: Fn(&mut GroupTest) -> &mut MetricTest,
+{
+    let p_values: Vec<Option<f64>> = tests
+        .iter_mut()
+        .map(|t| metric(t).result.map(|r| r.p_value))
+        .collect();
+    for (test, p) in tests.iter_mut().zip(stats::holm(&p_values)) {
+        metric(test).p_adjusted = p;
+    }
+}
+
+const COLUMNS: [&str; 18] = [
+    "Datasets",
+    "Analyses",
+    "OS_name",
+    "CPU",
+    "First",
+    "Second",
+    "N_first",
+    "N_second",
+    "Execution_time_secs_median_first",
+    "Execution_time_secs_median_second",
+    "Execution_time_statistic",
+    "Execution_time_p_value",
+    "Execution_time_p_adjusted",
+    "RAM_usage_Mb_median_first",
+    "RAM_usage_Mb_median_second",
+    "RAM_usage_statistic",
+    "RAM_usage_p_value",
+    "RAM_usage_p_adjusted",
+];
+
+fn fields(test: &GroupTest, first: &AppSelector, second: &AppSelector) -> Vec<String> {
+    let mut fields = vec![
+        test.dataset.clone(),
+        test.analysis.clone(),
+        test.os.clone(),
+        test.cpu.clone(),
+        first.to_string(),
+        second.to_string(),
+        test.n_first.to_string(),
+        test.n_second.to_string(),
+    ];
+    for metric in [&test.exec_time, &test.mem_usage] {
+        fields.push(format_optional(metric.median_first));
+        fields.push(format_optional(metric.median_second));
+        fields.push(format_optional(metric.result.map(|r| r.statistic)));
+        fields.push(format_optional(metric.result.map(|r| r.p_value)));
+        fields.push(format_optional(metric.p_adjusted));
+    }
+    fields
+}
+
+pub(crate) fn write_csv<W: Write>(
+    writer: &mut W,
+    tests: &[GroupTest],
+    first: &AppSelector,
+    second: &AppSelector,
+) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(COLUMNS)?;
+    for test in tests {
+        writer.write_record(fields(test, first, second))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub(crate) fn write_markdown<W: Write>(
+    writer: &mut W,
+    tests: &[GroupTest],
+    first: &AppSelector,
+    second: &AppSelector,
+) -> io::Result<()> {
+    writeln!(writer, "| {} |", COLUMNS.join(" | "))?;
+    writeln!(writer, "|{}", " --- |".repeat(COLUMNS.len()))?;
+    for test in tests {
+        let fields = fields(test, first, second);
+        writeln!(writer, "| {} |", fields.join(" | ").replace('\n', " "))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::row::RowBuilder;
+
+    #[test]
+    fn test_compare_versions() {
+        let mut rows = Vec::new();
+        for secs in [2.32, 2.33, 2.34, 2.26, 2.33] {
+            rows.push(
+                RowBuilder::new("SEGUL CLI", "Wu", secs)
+                    .version("v0.18.1")
+                    .build(),
+            );
+            rows.push(
+                RowBuilder::new("SEGUL CLI", "Wu", secs + 1.0)
+                    .version("v0.16.3")
+                    .build(),
+            );
+            rows.push(
+                RowBuilder::new("SEGUL CLI", "Shen", secs)
+                    .version("v0.18.1")
+                    .build(),
+            );
+        }
+        let first = AppSelector::new("segul cli", Some("v0.18.1"));
+        let second = AppSelector::new("SEGUL CLI", Some("v0.16.3"));
+        let tests = compare_apps(&rows, &first, &second, TestMethod::MannWhitney, false);
+        assert_eq!(1, tests.len());
+        assert_eq!("Wu", tests[0].dataset);
+        assert_eq!((5, 5), (tests[0].n_first, tests[0].n_second));
+        let result = tests[0].exec_time.result.unwrap();
+        assert!(result.p_value < 0.05);
+        assert_eq!(Some(result.p_value), tests[0].exec_time.p_adjusted);
+        assert!(tests[0].mem_usage.result.is_none());
+    }
+
+    #[test]
+    fn test_overlapping_selectors() {
+        let segul = AppSelector::new("SEGUL CLI", None);
+        let latest = AppSelector::new("segul cli", Some("v0.18.1"));
+        let older = AppSelector::new("SEGUL CLI", Some("v0.16.3"));
+        assert!(segul.overlaps(&segul));
+        assert!(segul.overlaps(&latest));
+        assert!(!latest.overlaps(&older));
+        assert!(!segul.overlaps(&AppSelector::new("AMAS", None)));
+
+        let rows = [
+            RowBuilder::new("SEGUL CLI", "Wu", 2.3)
+                .version("v0.18.1")
+                .build(),
+            RowBuilder::new("SEGUL CLI", "Wu", 3.3)
+                .version("v0.16.3")
+                .build(),
+        ];
+        let tests = compare_apps(&rows, &segul, &latest, TestMethod::MannWhitney, false);
+        assert!(tests.is_empty());
+    }
+}
@@ -0,0 +1,305 @@
+//! Regressions between two parsed result sets.
+//!
+//! Rows are matched by app, dataset, analysis, and machine, so that
+//! a new version of an app is compared with its old version. The
+//! change is measured on the median time and the median peak RSS.
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::error::{BenchParseError, ErrorReason};
+use crate::row::{format_optional, BenchRow};
+use crate::stats;
+use crate::summary::group_rows_by;
+
+/// Change of a metric in percent of the old median.
+#[derive(Debug, Clone, Copy)]
+pub struct Change {
+    pub old: Option<f64>,
+    pub new: Option<f64>,
+    pub percent: Option<f64>,
+}
+
+impl Change {
+    fn new(old: &[f64], new: &[f64]) -> Self {
+        let old = stats::median(old);
+        let new = stats::median(new);
+        let percent = match (old, new) {
+            (Some(old), Some(new)) if old > 0.0 => Some((new - old) / old * 100.0),
+            _ => None,
+        };
+        Self { old, new, percent }
+    }
+
+    fn exceeds(&self, threshold: f64) -> bool {
+        self.percent.is_some_and(|p| p > threshold)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupDiff {
+    pub app: String,
+    pub dataset: String,
+    pub analysis: String,
+    pub os: String,
+    pub cpu: String,
+    pub old_version: String,
+    pub new_version: String,
+    pub exec_time: Change,
+    /// Peak resident set size in kB.
+    pub peak_rss: Change,
+}
+
+impl GroupDiff {
+    /// Whether the time or the peak RSS grew by more than
+    /// `threshold` percent.
+    pub fn is_regression(&self, threshold: f64) -> bool {
+        self.exec_time.exceeds(threshold) || self.peak_rss.exceeds(threshold)
+    }
+}
+
+/// Reads the rows of a CSV written by the parser. Only the columns
+/// used in the diff are read, and the column names are matched
+/// case-insensitively, so older results can be read too.
+pub fn read_results(path: &Path) -> Result<Vec<BenchRow>, BenchParseError> {
+    let file = File::open(path).map_err(|e| BenchParseError::from(e).with_path(path))?;
+    parse_results(file).map_err(|e| e.with_path(path))
+}
+
+fn parse_results<R: Read>(input: R) -> Result<Vec<BenchRow>, BenchParseError> {
+    let mut reader = csv::Reader::from_reader(input);
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let required = |name: &str| {
+        column(name)
+            .ok_or_else(|| BenchParseError::new(ErrorReason::MissingColumn(name.to_string())))
+    };
+    let app = required("Apps")?;
+    let dataset = required("Datasets")?;
+    let analysis = required("Analyses")?;
+    let os = required("OS_name")?;
+    let cpu = required("CPU")?;
+    let exec_time = required("Execution_time_secs")?;
+    let version = column("Version");
+    let ram_usage = column("RAM_usage_kb");
+    let warmup = column("Warmup");
+    let outlier = column("Outlier");
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |p| p.line() as usize);
+        let field = |i: Option<usize>| i.and_then(|i| record.get(i)).unwrap_or_default();
+        let invalid =
+            |reason: ErrorReason, text: &str| BenchParseError::at_line(reason, line, text);
+        let mut row = BenchRow::new();
+        row.app = field(Some(app)).to_string();
+        row.version = field(version).to_string();
+        row.dataset = field(Some(dataset)).to_string();
+        row.analysis = field(Some(analysis)).to_string();
+        row.os = field(Some(os)).to_string();
+        row.cpu = field(Some(cpu)).to_string();
+        let secs = field(Some(exec_time));
+        row.exec_time_secs = secs
+            .parse()
+            .map_err(|_| invalid(ErrorReason::InvalidTime, secs))?;
+        row.ram_usage_kb = match field(ram_usage) {
+            "" => None,
+            kb => Some(
+                kb.parse()
+                    .map_err(|_| invalid(ErrorReason::InvalidMemory, kb))?,
+            ),
+        };
+        row.warmup = field(warmup).eq_ignore_ascii_case("TRUE");
+        row.outlier = field(outlier).eq_ignore_ascii_case("TRUE");
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Compares the groups found in both `old` and `new`,
+/// in the order they appear in `new`.
+pub fn diff_results(old: &[BenchRow], new: &[BenchRow], exclude_outliers: bool) -> Vec<GroupDiff> {
+    let old_groups = group_rows_by(old, exclude_outliers, key);
+    let old_index: HashMap<_, usize> = old_groups
+        .iter()
+        .enumerate()
+        .map(|(i, g)| (key(g[0]), i))
+        .collect();
+    let exec_times =
+        |rows: &[&BenchRow]| -> Vec<f64> { rows.iter().map(|r| r.exec_time_secs).collect() };
+    let peak_rss = |rows: &[&BenchRow]| -> Vec<f64> {
+        rows.iter()
+            .filter_map(|r| r.ram_usage_kb.map(|kb| kb as f64))
+            .collect()
+    };
+    group_rows_by(new, exclude_outliers, key)
+        .iter()
+        .filter_map(|group| {
+            let first = group[0];
+            let old_group = &old_groups[*old_index.get(&key(first))?];
+            Some(GroupDiff {
+                app: first.app.clone(),
+                dataset: first.dataset.clone(),
+                analysis: first.analysis.clone(),
+                os: first.os.clone(),
+                cpu: first.cpu.clone(),
+                old_version: versions(old_group),
+                new_version: versions(group),
+                exec_time: Change::new(&exec_times(old_group), &exec_times(group)),
+                peak_rss: Change::new(&peak_rss(old_group), &peak_rss(group)),
+            })
+        })
+        .collect()
+}
+
+// Leaves out the version, so the old and new versions of an app match.
+fn key(row: &BenchRow) -> (&str, &str, &str, String) {
+    (
+        row.app.as_str(),
+        row.dataset.as_str(),
+        row.analysis.as_str(),
+        row.machine(),
+    )
+}
+
+// Versions of a group, joined if the group has more than one.
+fn versions(rows: &[&BenchRow]) -> String {
+    let mut versions: Vec<&str> = Vec::new();
+    for row in rows {
+        if !versions.contains(&row.version.as_str()) {
+            versions.push(&row.version);
+        }
+    }
+    versions.join(" ")
+}
+
+/// Compares the results in `old` and `new` and writes
+/// the changes into `<output>.csv`.
+pub fn diff_files(
+    old: &Path,
+    new: &Path,
+    output: &Path,
+    threshold: f64,
+    exclude_outliers: bool,
+) -> Result<Vec<GroupDiff>, BenchParseError> {
+    let diffs = diff_results(&read_results(old)?, &read_results(new)?, exclude_outliers);
+    let output = output.with_extension("csv");
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut writer = BufWriter::new(File::create(output)?);
+    write_diffs(&mut writer, &diffs, threshold)?;
+    writer.flush()?;
+    Ok(diffs)
+}
+
+pub(crate) fn write_diffs<W: Write>(
+    writer: &mut W,
+    diffs: &[GroupDiff],
+    threshold: f64,
+) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record([
+        "Apps",
+        "Datasets",
+        "Analyses",
+        "OS_name",
+        "CPU",
+        "Old_version",
+        "New_version",
+        "Execution_time_secs_old",
+        "Execution_time_secs_new",
+        "Execution_time_change_percent",
+        "RAM_usage_kb_old",
+        "RAM_usage_kb_new",
+        "RAM_usage_change_percent",
+        "Regression",
+    ])?;
+    for d in diffs {
+        let mut record = vec![
+            d.app.clone(),
+            d.dataset.clone(),
+            d.analysis.clone(),
+            d.os.clone(),
+            d.cpu.clone(),
+            d.old_version.clone(),
+            d.new_version.clone(),
+        ];
+        for change in [d.exec_time, d.peak_rss] {
+            record.push(format_optional(change.old));
+            record.push(format_optional(change.new));
+            record.push(format_optional(change.percent));
+        }
+        let regression = if d.is_regression(threshold) {
+            "TRUE"
+        } else {
+            "FALSE"
+        };
+        record.push(regression.to_string());
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::row::RowBuilder;
+    use crate::writer::{OutputFormat, RowWriter};
+
+    #[test]
+    fn test_diff_results() {
+        let rows = |version: &str, rows: [(&str, f64, u64); 3]| {
+            rows.map(|(dataset, secs, kb)| {
+                RowBuilder::new("SEGUL CLI", dataset, secs)
+                    .version(version)
+                    .ram_kb(kb)
+                    .build()
+            })
+        };
+        let old = rows(
+            "v0.16.3",
+            [("Wu", 2.0, 1000), ("Wu", 2.2, 1000), ("Shen", 4.0, 2000)],
+        );
+        let new = rows(
+            "v0.18.1",
+            [("Wu", 2.5, 1000), ("Wu", 2.7, 1000), ("Chan", 1.0, 500)],
+        );
+        let diffs = diff_results(&old, &new, false);
+        assert_eq!(1, diffs.len());
+        assert_eq!("v0.16.3", diffs[0].old_version);
+        assert_eq!("v0.18.1", diffs[0].new_version);
+        let percent = diffs[0].exec_time.percent.unwrap();
+        assert!((percent - 23.809524).abs() < 1e-6);
+        assert_eq!(Some(0.0), diffs[0].peak_rss.percent);
+        assert!(diffs[0].is_regression(5.0));
+        assert!(!diffs[0].is_regression(25.0));
+    }
+
+    #[test]
+    fn test_parse_results() {
+        let mut output = Vec::new();
+        let mut rows = vec![
+            RowBuilder::new("SEGUL CLI", "Wu et al. 2018 (1.2 MBases, AA)", 2.5)
+                .version("v0.18.1")
+                .ram_kb(1000)
+                .build(),
+        ];
+        rows.push(rows[0].clone());
+        rows[1].warmup = true;
+        let mut writer = RowWriter::new(&mut output, OutputFormat::Csv).unwrap();
+        writer.write_rows(&[&rows[0], &rows[1]]).unwrap();
+        writer.finish().unwrap();
+        let parsed = parse_results(output.as_slice()).unwrap();
+        assert_eq!(2, parsed.len());
+        assert_eq!(rows[0].dataset, parsed[0].dataset);
+        assert_eq!(2.5, parsed[0].exec_time_secs);
+        assert_eq!(Some(1000), parsed[0].ram_usage_kb);
+        assert!(parsed[1].warmup);
+        let err = parse_results("Apps,Datasets\n".as_bytes()).unwrap_err();
+        assert!(matches!(err.reason, ErrorReason::MissingColumn(_)));
+    }
+}
@@ -0,0 +1,183 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::format::InputFormat;
+
+/// Why a benchmark log could not be parsed.
+#[derive(Debug)]
+pub enum ErrorReason {
+    Io(io::Error),
+    InvalidJson(serde_json::Error),
+    InvalidCsv(csv::Error),
+    Sqlite(Box<rusqlite::Error>),
+    Arrow(arrow_schema::ArrowError),
+    Parquet(parquet::errors::ParquetError),
+    InvalidFileName,
+    InvalidConfig(String),
+    MissingColumn(String),
+    UnknownFormat,
+    AmbiguousFormat(Vec<InputFormat>),
+    MissingHeaderValue,
+    MissingVersion,
+    MissingColumns { expected: usize, found: usize },
+    InvalidTime,
+    InvalidMemory,
+    InvalidCpuUsage,
+    InvalidCount,
+    IncompleteResult,
+    UnexpectedLine(InputFormat),
+    EmptyDataset,
+    InvalidReplicateCount { expected: usize, found: usize },
+    OverlappingApps { first: String, second: String },
+}
+
+impl fmt::Display for ErrorReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorReason::Io(e) => write!(f, "{}", e),
+            ErrorReason::InvalidJson(e) => write!(f, "invalid JSON: {}", e),
+            ErrorReason::InvalidCsv(e) => write!(f, "invalid CSV: {}", e),
+            ErrorReason::Sqlite(e) => write!(f, "SQLite error: {}", e),
+            ErrorReason::Arrow(e) => write!(f, "Arrow error: {}", e),
+            ErrorReason::Parquet(e) => write!(f, "Parquet error: {}", e),
+            ErrorReason::InvalidFileName => write!(f, "invalid file name"),
+            ErrorReason::InvalidConfig(e) => write!(f, "invalid config: {}", e),
+            ErrorReason::MissingColumn(column) => write!(f, "missing column: {}", column),
+            ErrorReason::UnknownFormat => write!(f, "unknown input format"),
+            ErrorReason::AmbiguousFormat(candidates) => write!(
+                f,
+                "ambiguous input format, candidates: {}",
+                candidates
+                    .iter()
+                    .map(|c| c.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ErrorReason::MissingHeaderValue => write!(f, "header line has no value"),
+            ErrorReason::MissingVersion => write!(f, "missing segul version"),
+            ErrorReason::MissingColumns { expected, found } => write!(
+                f,
+                "expected {} columns in the result line, found {}",
+                expected, found
+            ),
+            ErrorReason::InvalidTime => write!(f, "invalid execution time"),
+            ErrorReason::InvalidMemory => write!(f, "invalid memory usage"),
+            ErrorReason::InvalidCpuUsage => write!(f, "invalid CPU usage"),
+            ErrorReason::InvalidCount => write!(f, "invalid count"),
+            ErrorReason::IncompleteResult => write!(f, "incomplete verbose result"),
+            ErrorReason::UnexpectedLine(format) => {
+                write!(f, "unexpected line for the {} format", format)
+            }
+            ErrorReason::EmptyDataset => write!(f, "dataset has no results"),
+            ErrorReason::InvalidReplicateCount { expected, found } => write!(
+                f,
+                "expected {} replicates in the dataset, found {}",
+                expected, found
+            ),
+            ErrorReason::OverlappingApps { first, second } => write!(
+                f,
+                "the apps to compare overlap: {} and {}, select different versions",
+                first, second
+            ),
+        }
+    }
+}
+
+/// Parsing error with the location of the offending text.
+#[derive(Debug)]
+pub struct BenchParseError {
+    pub path: Option<PathBuf>,
+    pub line: Option<usize>,
+    pub text: Option<String>,
+    pub reason: ErrorReason,
+}
+
+impl BenchParseError {
+    pub fn new(reason: ErrorReason) -> Self {
+        Self {
+            path: None,
+            line: None,
+            text: None,
+            reason,
+        }
+    }
+
+    pub fn at_line(reason: ErrorReason, line: usize, text: &str) -> Self {
+        Self {
+            path: None,
+            line: Some(line),
+            text: Some(text.to_string()),
+            reason,
+        }
+    }
+
+    pub fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_path_buf());
+        self
+    }
+}
+
+impl fmt::Display for BenchParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}", path.display())?;
+            if let Some(line) = self.line {
+                write!(f, ":{}", line)?;
+            }
+            write!(f, ": ")?;
+        } else if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        write!(f, "{}", self.reason)?;
+        if let Some(text) = &self.text {
+            write!(f, " (found: \"{}\")", text)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for BenchParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.reason {
+            ErrorReason::Io(e) => Some(e),
+            ErrorReason::InvalidJson(e) => Some(e),
+            ErrorReason::InvalidCsv(e) => Some(e),
+            ErrorReason::Sqlite(e) => Some(e.as_ref()),
+            ErrorReason::Arrow(e) => Some(e),
+            ErrorReason::Parquet(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BenchParseError {
+    fn from(e: io::Error) -> Self {
+        Self::new(ErrorReason::Io(e))
+    }
+}
+
+impl From<csv::Error> for BenchParseError {
+    fn from(e: csv::Error) -> Self {
+        Self::new(ErrorReason::InvalidCsv(e))
+    }
+}
+
+impl From<rusqlite::Error> for BenchParseError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::new(ErrorReason::Sqlite(Box::new(e)))
+    }
+}
+
+impl From<arrow_schema::ArrowError> for BenchParseError {
+    fn from(e: arrow_schema::ArrowError) -> Self {
+        Self::new(ErrorReason::Arrow(e))
+    }
+}
+
+impl From<parquet::errors::ParquetError> for BenchParseError {
+    fn from(e: parquet::errors::ParquetError) -> Self {
+        Self::new(ErrorReason::Parquet(e))
+    }
+}
@@ -0,0 +1,128 @@
+//! Input format detection.
+use std::fmt;
+use std::io::BufRead;
+use std::str::FromStr;
+
+use crate::bsd;
+use crate::error::ErrorReason;
+use crate::gnu;
+
+// Enough to get past the machine information header
+// into the first dataset block.
+const SNIFF_LINES: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// GNU time `%E %M %P` lines.
+    Gnu,
+    /// GNU `time -v` blocks.
+    GnuVerbose,
+    /// BSD/macOS `time -l` blocks.
+    Bsd,
+    /// hyperfine `--export-json` files.
+    Hyperfine,
+}
+
+impl InputFormat {
+    pub const ALL: [InputFormat; 4] = [
+        InputFormat::Gnu,
+        InputFormat::GnuVerbose,
+        InputFormat::Bsd,
+        InputFormat::Hyperfine,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputFormat::Gnu => "gnu",
+            InputFormat::GnuVerbose => "gnu-verbose",
+            InputFormat::Bsd => "bsd",
+            InputFormat::Hyperfine => "hyperfine",
+        }
+    }
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        InputFormat::ALL
+            .iter()
+            .find(|f| f.name() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown input format: {}", s))
+    }
+}
+
+/// Detects the input format from the first lines of a benchmark file.
+pub fn detect_format<R: BufRead>(reader: R) -> Result<InputFormat, ErrorReason> {
+    let mut candidates = Vec::new();
+    for (i, line) in reader.lines().take(SNIFF_LINES).enumerate() {
+        let line = line.map_err(ErrorReason::Io)?;
+        if i == 0 && line.trim_start().starts_with('{') {
+            return Ok(InputFormat::Hyperfine);
+        }
+        if let Some(format) = match_result_line(&line) {
+            if !candidates.contains(&format) {
+                candidates.push(format);
+            }
+        }
+    }
+
+    match candidates.len() {
+        0 => Err(ErrorReason::UnknownFormat),
+        1 => Ok(candidates[0]),
+        _ => Err(ErrorReason::AmbiguousFormat(candidates)),
+    }
+}
+
+fn match_result_line(line: &str) -> Option<InputFormat> {
+    if gnu::is_verbose_start(line) {
+        Some(InputFormat::GnuVerbose)
+    } else if bsd::is_time_line(line) {
+        Some(InputFormat::Bsd)
+    } else if gnu::parse_compact_line(line).is_ok() {
+        Some(InputFormat::Gnu)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_format() {
+        let gnu = "segul 0.18.1\nBenchmarking SEGUL\nDataset path: a\n0:02.32 603756 142%\n";
+        let verbose = "Dataset path: a\n\tCommand being timed: \"segul\"\n\tExit status: 0\n";
+        let bsd = "Dataset path: a\n        2.60 real         3.00 user         0.28 sys\n";
+        let json = "{\n  \"results\": []\n}";
+        assert_eq!(InputFormat::Gnu, detect_format(gnu.as_bytes()).unwrap());
+        assert_eq!(
+            InputFormat::GnuVerbose,
+            detect_format(verbose.as_bytes()).unwrap()
+        );
+        assert_eq!(InputFormat::Bsd, detect_format(bsd.as_bytes()).unwrap());
+        assert_eq!(
+            InputFormat::Hyperfine,
+            detect_format(json.as_bytes()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_detect_ambiguous_format() {
+        let mixed = "0:02.32 603756 142%\n        2.60 real         3.00 user         0.28 sys\n";
+        match detect_format(mixed.as_bytes()) {
+            Err(ErrorReason::AmbiguousFormat(candidates)) => {
+                assert_eq!(vec![InputFormat::Gnu, InputFormat::Bsd], candidates)
+            }
+            _ => panic!("Expected ambiguous format"),
+        }
+    }
+}
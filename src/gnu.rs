@@ -0,0 +1,120 @@
+//! Result lines written by GNU time.
+//!
+//! The compact format is a single `%E %M %P` line per replicate.
+//! The verbose format (`time -v`) is a block of `key: value` lines
+//! that starts with `Command being timed` and ends with `Exit status`.
+use crate::error::ErrorReason;
+use crate::parser::validate_result;
+use crate::types::BenchmarkResult;
+
+const VERBOSE_START: &str = "Command being timed:";
+const VERBOSE_END: &str = "Exit status:";
+
+pub(crate) fn parse_compact_line(line: &str) -> Result<BenchmarkResult, ErrorReason> {
+    let bench_result = line.split_whitespace().collect::<Vec<&str>>();
+    if bench_result.len() != 3 {
+        return Err(ErrorReason::MissingColumns {
+            expected: 3,
+            found: bench_result.len(),
+        });
+    }
+    let mut bench = BenchmarkResult::new();
+    bench.exec_time = bench_result[0].to_string();
+    bench.mem_usage = bench_result[1].to_string();
+    bench.cpu_usage = bench_result[2].to_string();
+    validate_result(&bench)?;
+    Ok(bench)
+}
+
+pub(crate) fn is_verbose_start(line: &str) -> bool {
+    line.trim_start().starts_with(VERBOSE_START)
+}
+
+/// Adds a verbose line to the result.
+/// Returns true when the line closes the replicate block.
+pub(crate) fn parse_verbose_line(
+    bench: &mut BenchmarkResult,
+    line: &str,
+) -> Result<bool, ErrorReason> {
+    let line = line.trim();
+    if line.starts_with(VERBOSE_START) {
+        return Ok(false);
+    }
+    let (key, value) = match line.rsplit_once(": ") {
+        Some((key, value)) => (key, value.trim()),
+        None => return Err(ErrorReason::MissingHeaderValue),
+    };
+    match key {
+        "User time (seconds)" => bench.user_time = Some(parse_secs(value)?),
+        "System time (seconds)" => bench.system_time = Some(parse_secs(value)?),
+        "Percent of CPU this job got" => {
+            // GNU time prints `?%` when the elapsed time is zero.
+            bench.cpu_usage = value.replace('?', "0");
+        }
+        key if key.starts_with("Elapsed (wall clock) time") => {
+            bench.exec_time = value.to_string();
+        }
+        "Maximum resident set size (kbytes)" => bench.mem_usage = value.to_string(),
+        "Major (requiring I/O) page faults" => bench.major_page_faults = Some(parse_count(value)?),
+        "Minor (reclaiming a frame) page faults" => {
+            bench.minor_page_faults = Some(parse_count(value)?)
+        }
+        "Voluntary context switches" => bench.voluntary_switches = Some(parse_count(value)?),
+        "Involuntary context switches" => bench.involuntary_switches = Some(parse_count(value)?),
+        "File system inputs" => bench.fs_inputs = Some(parse_count(value)?),
+        "File system outputs" => bench.fs_outputs = Some(parse_count(value)?),
+        "Exit status" => {
+            bench.exit_status = Some(value.parse().map_err(|_| ErrorReason::InvalidCount)?);
+        }
+        _ => (),
+    }
+
+    if line.starts_with(VERBOSE_END) {
+        validate_result(bench)?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+fn parse_secs(value: &str) -> Result<f64, ErrorReason> {
+    value.parse().map_err(|_| ErrorReason::InvalidTime)
+}
+
+fn parse_count(value: &str) -> Result<u64, ErrorReason> {
+    value.parse().map_err(|_| ErrorReason::InvalidCount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_verbose_block() {
+        let block = "\tCommand being timed: \"segul alignment concat -d alignments\"
+            \tUser time (seconds): 2.71
+            \tSystem time (seconds): 0.58
+            \tPercent of CPU this job got: 142%
+            \tElapsed (wall clock) time (h:mm:ss or m:ss): 0:02.32
+            \tMaximum resident set size (kbytes): 603756
+            \tMajor (requiring I/O) page faults: 2
+            \tMinor (reclaiming a frame) page faults: 150123
+            \tVoluntary context switches: 1234
+            \tInvoluntary context switches: 56
+            \tFile system inputs: 16
+            \tFile system outputs: 8
+            \tPage size (bytes): 4096
+            \tExit status: 0";
+        let mut bench = BenchmarkResult::new();
+        let done = block
+            .lines()
+            .map(|l| parse_verbose_line(&mut bench, l).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(Some(&true), done.last());
+        assert_eq!("0:02.32", bench.exec_time);
+        assert_eq!("603756", bench.mem_usage);
+        assert_eq!("142%", bench.cpu_usage);
+        assert_eq!(Some(0.58), bench.system_time);
+        assert_eq!(Some(150123), bench.minor_page_faults);
+        assert_eq!(Some(0), bench.exit_status);
+    }
+}
@@ -0,0 +1,132 @@
+//! Benchmark results exported by `hyperfine --export-json`.
+//!
+//! Each command becomes a record with one replicate per run.
+//! The command string takes the place of the `Benchmarking` line,
+//! and the dataset is taken from the command parameters if any.
+use std::io::Read;
+
+use serde::Deserialize;
+
+use crate::error::{BenchParseError, ErrorReason};
+use crate::parser::format_elapsed;
+use crate::types::{Benchmark, BenchmarkResult, Dataset, Records};
+
+#[derive(Debug, Deserialize)]
+struct Export {
+    results: Vec<CommandResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandResult {
+    command: String,
+    times: Vec<f64>,
+    #[serde(default)]
+    memory_usage_byte: Option<Vec<u64>>,
+    #[serde(default)]
+    exit_codes: Option<Vec<Option<i32>>>,
+    #[serde(default)]
+    parameters: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+/// Reads a hyperfine JSON export into records.
+pub fn read_records<R: Read>(reader: R) -> Result<Vec<Records>, BenchParseError> {
+    let export: Export = serde_json::from_reader(reader).map_err(|e| {
+        let line = e.line();
+        let mut err = BenchParseError::new(ErrorReason::InvalidJson(e));
+        if line > 0 {
+            err.line = Some(line);
+        }
+        err
+    })?;
+    export
+        .results
+        .iter()
+        .enumerate()
+        .map(|(i, cmd)| parse_command(cmd, i + 1))
+        .collect()
+}
+
+fn parse_command(cmd: &CommandResult, position: usize) -> Result<Records, BenchParseError> {
+    let mut dataset = Dataset::new();
+    dataset.name = dataset_name(cmd);
+    for (i, time) in cmd.times.iter().enumerate() {
+        let mut bench = BenchmarkResult::new();
+        bench.exec_time = format_elapsed(*time);
+        if let Some(mem) = cmd.memory_usage_byte.as_ref().and_then(|m| m.get(i)) {
+            bench.mem_usage = (mem / 1024).to_string();
+        }
+        bench.exit_status = cmd
+            .exit_codes
+            .as_ref()
+            .and_then(|codes| codes.get(i).copied().flatten());
+        dataset.result.push(bench);
+    }
+    if !dataset.has_record() {
+        return Err(BenchParseError {
+            path: None,
+            line: None,
+            text: Some(cmd.command.clone()),
+            reason: ErrorReason::EmptyDataset,
+        });
+    }
+
+    let mut recs = Records::new();
+    let mut bench = Benchmark::new();
+    bench.bench = cmd.command.clone();
+    bench.line = position;
+    bench.dataset.push(dataset);
+    recs.benchmark = bench;
+    Ok(recs)
+}
+
+// Parameter values are joined so that a parameterized dataset path
+// can be matched against the publication names. Otherwise, the dataset
+// path is somewhere in the command itself.
+fn dataset_name(cmd: &CommandResult) -> String {
+    match &cmd.parameters {
+        Some(params) if !params.is_empty() => params
+            .values()
+            .map(|v| match v {
+                serde_json::Value::String(s) => s.clone(),
+                v => v.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => cmd.command.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_hyperfine_records() {
+        let input = r#"{
+            "results": [
+                {
+                    "command": "segul align concat -d alignments/wu_2018_aa_loci",
+                    "mean": 2.605,
+                    "times": [2.6, 2.61],
+                    "memory_usage_byte": [391479296, 391790592],
+                    "exit_codes": [0, 0]
+                },
+                {
+                    "command": "python3 AMAS.py concat -i {dataset}/*",
+                    "mean": 20.5,
+                    "times": [20.5],
+                    "parameters": {"dataset": "alignments/shen_2018_loci_aa"}
+                }
+            ]
+        }"#;
+        let records = read_records(input.as_bytes()).unwrap();
+        assert_eq!(2, records.len());
+        let dataset = &records[0].benchmark.dataset[0];
+        assert_eq!(2, dataset.result.len());
+        assert_eq!("0:02.61", dataset.result[1].exec_time);
+        assert_eq!("382608", dataset.result[1].mem_usage);
+        let dataset = &records[1].benchmark.dataset[0];
+        assert_eq!("alignments/shen_2018_loci_aa", dataset.name);
+        assert!(dataset.result[0].mem_usage.is_empty());
+    }
+}
@@ -0,0 +1,52 @@
+//! Parse benchmark logs from segul-bench into tabular records.
+//!
+//! The inputs are GNU time logs, compact (`-f "%E %M %P"`) or verbose
+//! (`-v`), BSD/macOS `time -l` logs, and hyperfine JSON exports. The
+//! [`BenchReader`] iterates over a raw log and yields one [`Records`] per
+//! dataset block. The [`Parser`] reads a set of files and writes the rows
+//! as CSV, TSV, JSON, NDJSON, Parquet, or Arrow IPC (see [`OutputFormat`]),
+//! and optionally into a SQLite database.
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use bench_parser::BenchReader;
+//!
+//! let file = File::open("concat_bench_raw_OpenSUSE_2022-10-04.txt").unwrap();
+//! for rec in BenchReader::new(file) {
+//!     let rec = rec.expect("Malformed benchmark log");
+//!     println!("{}: {}", rec.benchmark.bench, rec.machine.cpu);
+//! }
+//! ```
+pub mod baseline;
+mod bsd;
+mod columnar;
+pub mod compare;
+pub mod diff;
+pub mod error;
+pub mod format;
+mod gnu;
+pub mod hyperfine;
+mod machine;
+pub mod outlier;
+pub mod parser;
+pub mod reader;
+pub mod registry;
+pub mod row;
+pub mod scaling;
+mod sqlite;
+mod stats;
+pub mod summary;
+pub mod types;
+pub mod writer;
+
+pub use compare::{AppSelector, TestMethod};
+pub use error::{BenchParseError, ErrorReason};
+pub use format::{detect_format, InputFormat};
+pub use outlier::OutlierMethod;
+pub use parser::{ParseSummary, Parser, ParserBuilder, WarmupOutput};
+pub use reader::BenchReader;
+pub use registry::{AnalysisRegistry, AppRegistry, DatasetRegistry};
+pub use row::BenchRow;
+pub use stats::{LinearFit, TestResult};
+pub use types::{AppType, Apps, Benchmark, BenchmarkResult, Dataset, MachineInfo, Pubs, Records};
+pub use writer::OutputFormat;
@@ -0,0 +1,189 @@
+//! Machine information in the log header.
+//!
+//! The harness writes a `# key: value` header (host, cpu, cores,
+//! threads, ram, os, kernel). Older logs only have the lscpu and
+//! uname output, which is used as the fallback.
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::error::ErrorReason;
+use crate::types::MachineInfo;
+
+const HEADER_PREFIX: char = '#';
+
+// Keys of the structured header. Other keys are ignored.
+const HEADER_KEYS: [&str; 7] = ["host", "cpu", "os", "kernel", "cores", "threads", "ram"];
+
+// `key: value` lines of the log that are not lscpu fields.
+const LOG_KEYS: [&str; 2] = ["Analysis", "Dataset path"];
+
+/// Values of the structured header. They take precedence
+/// over the values parsed from the lscpu and uname output.
+#[derive(Debug, Default)]
+pub(crate) struct Header {
+    host: Option<String>,
+    cpu: Option<String>,
+    os: Option<String>,
+    kernel: Option<String>,
+    cores: Option<usize>,
+    threads: Option<usize>,
+    total_ram_kb: Option<u64>,
+}
+
+impl Header {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn apply(&self, machine: &MachineInfo) -> MachineInfo {
+        let mut machine = machine.clone();
+        let or = |value: &Option<String>, fallback: String| value.clone().unwrap_or(fallback);
+        machine.host = or(&self.host, machine.host);
+        machine.cpu = or(&self.cpu, machine.cpu);
+        machine.os = or(&self.os, machine.os);
+        machine.kernel = or(&self.kernel, machine.kernel);
+        machine.cores = self.cores.or(machine.cores);
+        machine.threads = self.threads.or(machine.threads);
+        machine.total_ram_kb = self.total_ram_kb.or(machine.total_ram_kb);
+        machine
+    }
+}
+
+pub(crate) fn is_header_line(line: &str) -> bool {
+    line.starts_with(HEADER_PREFIX)
+}
+
+/// Adds a `# key: value` header line. Unknown keys are ignored.
+pub(crate) fn parse_header_line(header: &mut Header, line: &str) -> Result<(), ErrorReason> {
+    let line = line.trim_start_matches(HEADER_PREFIX).trim();
+    let (key, value) = match line.split_once(':') {
+        Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+        None => return Ok(()),
+    };
+    if !HEADER_KEYS.contains(&key.as_str()) {
+        return Ok(());
+    }
+    if value.is_empty() {
+        return Err(ErrorReason::MissingHeaderValue);
+    }
+    match key.as_str() {
+        "host" => header.host = Some(value.to_string()),
+        "cpu" => header.cpu = Some(value.to_string()),
+        "os" => header.os = Some(value.to_string()),
+        "kernel" => header.kernel = Some(value.to_string()),
+        "cores" => header.cores = Some(parse_count(value)?),
+        "threads" => header.threads = Some(parse_count(value)?),
+        "ram" => header.total_ram_kb = Some(parse_ram(value)?),
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Adds the uname and meminfo lines used as the fallback.
+/// Returns false if the line is neither.
+pub(crate) fn parse_fallback_line(
+    machine: &mut MachineInfo,
+    line: &str,
+) -> Result<bool, ErrorReason> {
+    lazy_static! {
+        // `uname -r`, e.g. 5.16.11-1-default
+        static ref KERNEL_RELEASE: Regex =
+            Regex::new(r"^\d+\.\d+\.\d+\S*$").expect("Failed to compile regex");
+        static ref DARWIN_VERSION: Regex =
+            Regex::new(r"^Darwin Kernel Version ([\d.]+):").expect("Failed to compile regex");
+    };
+
+    let line = line.trim();
+    if KERNEL_RELEASE.is_match(line) {
+        machine.kernel = line.to_string();
+    } else if let Some(caps) = DARWIN_VERSION.captures(line) {
+        machine.kernel = caps[1].to_string();
+    } else if let Some(value) = line.strip_prefix("MemTotal:") {
+        machine.total_ram_kb = Some(parse_ram(value.trim())?);
+    } else {
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Adds a `key: value` line of the lscpu output.
+/// Lines without a value, e.g. the section titles, are ignored.
+pub(crate) fn parse_lscpu_line(machine: &mut MachineInfo, line: &str) {
+    if let Some((key, value)) = line.split_once(':') {
+        let (key, value) = (key.trim(), value.trim());
+        if !key.is_empty() && !value.is_empty() && !LOG_KEYS.contains(&key) {
+            machine.lscpu.insert(key.to_string(), value.to_string());
+        }
+    }
+}
+
+fn parse_count(value: &str) -> Result<usize, ErrorReason> {
+    value.parse().map_err(|_| ErrorReason::InvalidCount)
+}
+
+/// Parses a memory size into kB, e.g. `64 GB`, `65536M`, or `65794640 kB`.
+/// Units are binary, as reported by `free` and `/proc/meminfo`.
+/// Values without a unit are in kB.
+fn parse_ram(value: &str) -> Result<u64, ErrorReason> {
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (size, unit) = value.split_at(split);
+    let size: f64 = size.parse().map_err(|_| ErrorReason::InvalidMemory)?;
+    let multiplier = match unit.trim().to_lowercase().as_str() {
+        "b" => 1.0 / 1024.0,
+        "" | "k" | "kb" | "kib" => 1.0,
+        "m" | "mb" | "mib" => 1024.0,
+        "g" | "gb" | "gib" => 1024.0 * 1024.0,
+        "t" | "tb" | "tib" => 1024.0 * 1024.0 * 1024.0,
+        _ => return Err(ErrorReason::InvalidMemory),
+    };
+    Ok((size * multiplier).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_precedence() {
+        let mut header = Header::new();
+        parse_header_line(&mut header, "# cpu: AMD Ryzen 9 3900X").unwrap();
+        parse_header_line(&mut header, "# ram: 64 GB").unwrap();
+        let mut machine = MachineInfo::new();
+        machine.cpu = String::from("AMD Ryzen 9 3900X 12-Core Processor");
+        for line in [
+            "CPU(s):              24",
+            "Thread(s) per core:  2",
+            "Core(s) per socket:  12",
+            "Caches (sum of all):",
+        ] {
+            parse_lscpu_line(&mut machine, line);
+        }
+        parse_fallback_line(&mut machine, "5.16.11-1-default").unwrap();
+        let machine = header.apply(&machine);
+        assert_eq!("AMD Ryzen 9 3900X", machine.cpu);
+        assert_eq!(Some(67108864), machine.total_ram_kb);
+        assert_eq!(Some(12), machine.physical_cores());
+        assert_eq!(Some(24), machine.logical_cpus());
+        assert_eq!(Some("2"), machine.lscpu_value("Thread(s) per core"));
+        assert_eq!(3, machine.lscpu.len());
+        assert_eq!("5.16.11-1-default", machine.kernel);
+    }
+
+    #[test]
+    fn test_unknown_header_keys() {
+        let mut header = Header::new();
+        parse_header_line(&mut header, "# notes:").unwrap();
+        parse_header_line(&mut header, "# run by the harness").unwrap();
+        assert!(parse_header_line(&mut header, "# cpu:").is_err());
+    }
+
+    #[test]
+    fn test_parse_ram() {
+        assert_eq!(65794640, parse_ram("65794640 kB").unwrap());
+        assert_eq!(8388608, parse_ram("8GiB").unwrap());
+        assert_eq!(16, parse_ram("16").unwrap());
+        assert!(parse_ram("lots").is_err());
+    }
+}
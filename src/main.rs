@@ -1,15 +1,164 @@
 mod cli;
-mod parser;
-mod types;
 
-use parser::Parser;
+use std::path::PathBuf;
+use std::process;
+
+use bench_parser::{
+    diff, AnalysisRegistry, AppRegistry, BenchParseError, DatasetRegistry, ParseSummary, Parser,
+    ParserBuilder,
+};
+use clap::ArgMatches;
 
 fn main() {
     let matches = cli::parser_arg();
-    let input_files = cli::parse_input(&matches);
-    let output = cli::parse_output(&matches);
-    let dataset_size = cli::parse_dataset_size(&matches);
-    Parser::new(&input_files, output, dataset_size)
-        .parse_benchmark()
-        .expect("Failed parsing benchmark");
+    match matches.subcommand() {
+        Some(("compare", compare_matches)) => compare(compare_matches),
+        Some(("diff", diff_matches)) => diff(diff_matches),
+        _ => parse(&matches),
+    }
+}
+
+fn parse(matches: &ArgMatches) {
+    let input_files = cli::parse_input(matches);
+    let summarize = cli::parse_summarize(matches);
+    let scaling = cli::parse_scaling(matches);
+    let mut builder = builder(matches, &input_files)
+        .summarize(summarize)
+        .scaling(scaling)
+        .output_format(cli::parse_output_format(matches));
+    if cli::parse_discard_warmup(matches).is_some() {
+        builder = builder.warmup_output(cli::parse_warmup_output(matches));
+    }
+    if let Some(path) = cli::parse_sqlite(matches) {
+        builder = builder.sqlite(path);
+    }
+    if let Some(baseline) = cli::parse_baseline(matches) {
+        builder = builder.baseline(baseline);
+    }
+    let summary = exit_on_error(builder.build().parse_benchmark());
+    report(&summary, input_files.len());
+}
+
+fn compare(matches: &ArgMatches) {
+    let input_files = cli::parse_input(matches);
+    let first = cli::parse_first(matches);
+    let second = cli::parse_second(matches);
+    let method = cli::parse_test(matches);
+    let markdown = cli::parse_markdown(matches);
+    let parser = builder(matches, &input_files).build();
+    let summary = exit_on_error(parser.compare(&first, &second, method, markdown));
+    report(&summary, input_files.len());
+}
+
+fn diff(matches: &ArgMatches) {
+    let threshold = cli::parse_threshold(matches);
+    let diffs = exit_on_error(diff::diff_files(
+        cli::parse_old(matches),
+        cli::parse_new(matches),
+        cli::parse_output(matches),
+        threshold,
+        cli::parse_exclude_outliers(matches),
+    ));
+    let regressions: Vec<_> = diffs
+        .iter()
+        .filter(|d| d.is_regression(threshold))
+        .collect();
+    for d in &regressions {
+        eprintln!(
+            "Regression: {} {} -> {}, {}, {}, {} ({}): time {}%, peak RSS {}%",
+            d.app,
+            d.old_version,
+            d.new_version,
+            d.dataset,
+            d.analysis,
+            d.os,
+            d.cpu,
+            format_percent(d.exec_time.percent),
+            format_percent(d.peak_rss.percent)
+        );
+    }
+    println!(
+        "Compared {} groups. Regressions above {}%: {}",
+        diffs.len(),
+        threshold,
+        regressions.len()
+    );
+    // Errors exit with 1, so a regression has its own code.
+    if !regressions.is_empty() {
+        process::exit(3);
+    }
+}
+
+fn format_percent(percent: Option<f64>) -> String {
+    match percent {
+        Some(p) => format!("{:+.1}", p),
+        None => String::from("NA"),
+    }
+}
+
+// Applies the options shared by every command.
+fn builder<'a>(matches: &'a ArgMatches, input_files: &'a [PathBuf]) -> ParserBuilder<'a> {
+    let output = cli::parse_output(matches);
+    let lenient = cli::parse_lenient(matches);
+    let mut builder = Parser::builder(input_files, output).lenient(lenient);
+    if let Some(replicates) = cli::parse_expected_replicates(matches) {
+        builder = builder.expect_replicates(replicates);
+    }
+    if let Some(warmup) = cli::parse_discard_warmup(matches) {
+        builder = builder.discard_warmup(warmup);
+    }
+    if let Some(method) = cli::parse_outliers(matches) {
+        builder = builder
+            .outliers(method)
+            .exclude_outliers(cli::parse_exclude_outliers(matches));
+    }
+    if let Some(format) = cli::parse_format(matches) {
+        builder = builder.format(format);
+    }
+    if let Some(path) = cli::parse_datasets(matches) {
+        builder = builder.datasets(exit_on_error(DatasetRegistry::from_file(path)));
+    }
+    if let Some(path) = cli::parse_apps(matches) {
+        builder = builder.apps(exit_on_error(AppRegistry::from_file(path)));
+    }
+    if let Some(path) = cli::parse_analyses(matches) {
+        builder = builder.analyses(exit_on_error(AnalysisRegistry::from_file(path)));
+    }
+    builder
+}
+
+fn report(summary: &ParseSummary, input_count: usize) {
+    for e in &summary.warnings {
+        eprintln!("Warning: skipped dataset: {}", e);
+    }
+    for path in &summary.skipped {
+        eprintln!("Warning: skipped {}: no datasets found", path.display());
+    }
+    for e in &summary.errors {
+        eprintln!("Error: {}", e);
+    }
+    println!(
+        "Parsed {} of {} files. Failed: {}. Skipped files: {}. Skipped datasets: {}",
+        summary.parsed.len(),
+        input_count,
+        summary.errors.len(),
+        summary.skipped.len(),
+        summary.warnings.len()
+    );
+    if !summary.errors.is_empty() {
+        process::exit(1);
+    }
+    if !summary.skipped.is_empty() || !summary.warnings.is_empty() {
+        process::exit(2);
+    }
+}
+
+fn exit_on_error<T>(result: Result<T, BenchParseError>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
 }
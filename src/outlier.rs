@@ -0,0 +1,119 @@
+//! Outlier detection within a dataset block.
+//!
+//! A replicate is an outlier if its execution time or its memory
+//! usage is an outlier among the replicates of the same block.
+//! Warm-up replicates are not used and never flagged.
+use std::fmt;
+use std::str::FromStr;
+
+use crate::row::BenchRow;
+use crate::stats;
+
+// Tukey's fences.
+const IQR_FACTOR: f64 = 1.5;
+// Iglewicz and Hoaglin's modified z-score cutoff.
+const MAD_CUTOFF: f64 = 3.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutlierMethod {
+    /// Outside 1.5 times the interquartile range from the quartiles.
+    Iqr,
+    /// Modified z-score above 3.5, using the median absolute deviation.
+    Mad,
+}
+
+impl OutlierMethod {
+    fn is_outlier(&self, values: &[f64], value: f64) -> bool {
+        match self {
+            OutlierMethod::Iqr => {
+                let (q1, q3) = match (stats::quantile(values, 0.25), stats::quantile(values, 0.75))
+                {
+                    (Some(q1), Some(q3)) => (q1, q3),
+                    _ => return false,
+                };
+                let iqr = q3 - q1;
+                value < q1 - IQR_FACTOR * iqr || value > q3 + IQR_FACTOR * iqr
+            }
+            OutlierMethod::Mad => {
+                let median = match stats::median(values) {
+                    Some(median) => median,
+                    None => return false,
+                };
+                let deviations: Vec<f64> = values.iter().map(|v| (v - median).abs()).collect();
+                match stats::median(&deviations) {
+                    Some(mad) if mad > 0.0 => 0.6745 * (value - median).abs() / mad > MAD_CUTOFF,
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for OutlierMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutlierMethod::Iqr => write!(f, "iqr"),
+            OutlierMethod::Mad => write!(f, "mad"),
+        }
+    }
+}
+
+impl FromStr for OutlierMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "iqr" => Ok(OutlierMethod::Iqr),
+            "mad" => Ok(OutlierMethod::Mad),
+            _ => Err(format!("Unknown outlier method: {}", s)),
+        }
+    }
+}
+
+/// Flags the outliers among the rows of a dataset block.
+pub fn flag_outliers(rows: &mut [BenchRow], method: OutlierMethod) {
+    let exec_times: Vec<f64> = rows
+        .iter()
+        .filter(|r| !r.warmup)
+        .map(|r| r.exec_time_secs)
+        .collect();
+    let mem_usages: Vec<f64> = rows
+        .iter()
+        .filter(|r| !r.warmup)
+        .filter_map(|r| r.ram_usage_kb)
+        .map(|kb| kb as f64)
+        .collect();
+    for row in rows.iter_mut().filter(|r| !r.warmup) {
+        row.outlier = method.is_outlier(&exec_times, row.exec_time_secs)
+            || row
+                .ram_usage_kb
+                .is_some_and(|kb| method.is_outlier(&mem_usages, kb as f64));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::row::RowBuilder;
+
+    #[test]
+    fn test_flag_outliers() {
+        let secs = [3.89, 3.64, 3.69, 3.65, 3.63, 3.61, 3.56, 3.58, 3.59, 3.55];
+        for method in [OutlierMethod::Iqr, OutlierMethod::Mad] {
+            let mut rows = secs.map(|s| RowBuilder::new("SEGUL CLI", "Wu", s).build());
+            flag_outliers(&mut rows, method);
+            let flagged: Vec<bool> = rows.iter().map(|r| r.outlier).collect();
+            assert!(flagged[0], "{}", method);
+            assert_eq!(1, flagged.iter().filter(|f| **f).count(), "{}", method);
+        }
+    }
+
+    #[test]
+    fn test_warmup_not_flagged() {
+        let mut rows =
+            [3.89, 3.64, 3.69, 3.65, 3.63].map(|s| RowBuilder::new("SEGUL CLI", "Wu", s).build());
+        rows[0].warmup = true;
+        flag_outliers(&mut rows, OutlierMethod::Iqr);
+        assert!(rows.iter().all(|r| !r.outlier));
+    }
+}
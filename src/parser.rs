@@ -1,500 +1,645 @@
 use std::fs;
-use std::io::Result;
-use std::io::{prelude::*, BufWriter};
+use std::io::{prelude::*, BufReader, BufWriter};
 use std::path::PathBuf;
-use std::{fs::File, io::BufReader, path::Path};
+use std::str::FromStr;
+use std::{fs::File, path::Path};
 
 use lazy_static::lazy_static;
 use regex::Regex;
 
-use crate::types::{Apps, Benchmark, BenchmarkResult, Dataset, Pubs, Records};
-
+use crate::baseline;
+use crate::compare::{self, AppSelector, TestMethod};
+use crate::error::{BenchParseError, ErrorReason};
+use crate::format::{detect_format, InputFormat};
+use crate::hyperfine;
+use crate::outlier::{self, OutlierMethod};
+use crate::reader::BenchReader;
+use crate::registry::{AnalysisRegistry, AppRegistry, DatasetRegistry};
+use crate::row::BenchRow;
+use crate::scaling;
+use crate::sqlite;
+use crate::summary;
+use crate::types::{AppType, Apps, BenchmarkResult, Records};
+use crate::writer::{OutputFormat, RowWriter};
+
+/// Parses GNU time, BSD `time -l`, and hyperfine benchmark files and
+/// writes the rows in the [`OutputFormat`], optionally into SQLite too.
+///
+/// Use [`Parser::builder`] to construct it.
 pub struct Parser<'a> {
-    pub input: &'a [PathBuf],
-    pub output: &'a Path,
-    pub dataset_size: usize,
+    input: &'a [PathBuf],
+    output: &'a Path,
+    expected_replicates: Option<usize>,
+    format: Option<InputFormat>,
+    lenient: bool,
+    datasets: DatasetRegistry,
+    apps: AppRegistry,
+    analyses: AnalysisRegistry,
+    summarize: bool,
+    scaling: bool,
+    warmup: usize,
+    warmup_output: WarmupOutput,
+    outliers: Option<OutlierMethod>,
+    exclude_outliers: bool,
+    baseline: Option<String>,
+    output_format: OutputFormat,
+    sqlite: Option<PathBuf>,
 }
 
-impl<'a> Parser<'a> {
-    pub fn new(input: &'a [PathBuf], output: &'a Path, dataset_size: usize) -> Self {
+/// Outcome of parsing a set of benchmark files.
+#[derive(Debug, Default)]
+pub struct ParseSummary {
+    pub parsed: Vec<PathBuf>,
+    pub errors: Vec<BenchParseError>,
+    /// Files without any dataset, such as an empty hyperfine export.
+    pub skipped: Vec<PathBuf>,
+    /// Dataset blocks dropped in lenient mode.
+    pub warnings: Vec<BenchParseError>,
+}
+
+/// What to do with the warm-up replicates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmupOutput {
+    /// Leave them out of the output.
+    Drop,
+    /// Write them into `<output>.warmup.csv`, or the extension
+    /// of the output format.
+    File,
+    /// Keep them in the output with `Warmup` set to `TRUE`.
+    Flag,
+}
+
+impl FromStr for WarmupOutput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop" => Ok(WarmupOutput::Drop),
+            "file" => Ok(WarmupOutput::File),
+            "flag" => Ok(WarmupOutput::Flag),
+            _ => Err(format!("Unknown warm-up output: {}", s)),
+        }
+    }
+}
+
+pub struct ParserBuilder<'a> {
+    input: &'a [PathBuf],
+    output: &'a Path,
+    expected_replicates: Option<usize>,
+    format: Option<InputFormat>,
+    lenient: bool,
+    datasets: DatasetRegistry,
+    apps: AppRegistry,
+    analyses: AnalysisRegistry,
+    summarize: bool,
+    scaling: bool,
+    warmup: usize,
+    warmup_output: WarmupOutput,
+    outliers: Option<OutlierMethod>,
+    exclude_outliers: bool,
+    baseline: Option<String>,
+    output_format: OutputFormat,
+    sqlite: Option<PathBuf>,
+}
+
+impl<'a> ParserBuilder<'a> {
+    pub fn new(input: &'a [PathBuf], output: &'a Path) -> Self {
         Self {
             input,
             output,
-            dataset_size,
+            expected_replicates: None,
+            format: None,
+            lenient: false,
+            datasets: DatasetRegistry::default(),
+            apps: AppRegistry::default(),
+            analyses: AnalysisRegistry::default(),
+            summarize: false,
+            scaling: false,
+            warmup: 0,
+            warmup_output: WarmupOutput::Drop,
+            outliers: None,
+            exclude_outliers: false,
+            baseline: None,
+            output_format: OutputFormat::default(),
+            sqlite: None,
         }
     }
 
-    pub fn parse_benchmark(&self) -> Result<()> {
-        let mut writer = self.write_records().expect("Failed writing records");
-        self.print_input();
-        self.input.iter().for_each(|f| {
-            self.parse_file(f, &mut writer)
-                .expect("Failed to parse text");
-        });
-        Ok(())
+    /// Validate that every dataset has exactly `replicates` results.
+    /// By default, the replicate count is taken from each dataset block.
+    pub fn expect_replicates(mut self, replicates: usize) -> Self {
+        self.expected_replicates = Some(replicates);
+        self
     }
 
-    fn write_records(&self) -> Result<BufWriter<File>> {
-        let output = self.output.with_extension("csv");
-        fs::create_dir_all(&output.parent().expect("Failed creating output directory"))?;
-        let file = File::create(output)?;
-        let mut writer = BufWriter::new(file);
-        writeln!(
-            writer,
-            "Apps,Version,\
-            Pubs,Datasets,NTAX,Character_counts,Alignment_counts,Site_counts,\
-            Datatype,Analyses,Platform,App_type,OS_name,CPU,Benchmark_dates,Latest_bench,\
-            Execution_time,RAM_usage_kb,Percent_CPU_usage,\
-            Execution_time_secs,RAM_usage_Mb\
-        "
-        )?;
-        Ok(writer)
-    }
-
-    fn parse_file<W: Write>(&self, input: &Path, writer: &mut W) -> Result<()> {
-        let file = File::open(input)?;
-        let reader = BufReader::new(file);
-        let records = BenchReader::new(reader, self.dataset_size);
-        let file_stem = input
-            .file_stem()
-            .expect("Failed parsing file stem")
-            .to_str()
-            .expect("Failed parsing file stem to str");
-        let analysis = self.parse_analysis_name(file_stem);
-        let analysis_name = self.match_analyses(analysis);
-        let date = parse_date(&file_stem);
-        for rec in records {
-            for dataset in rec.benchmark.dataset {
-                let dataset_size = dataset.result.len();
-                if dataset_size != self.dataset_size || !dataset.has_record() {
-                    panic!(
-                        "Invalid dataset result of {} for {}. \
-                        Expected {} records. Found : {}. Offending file: {}",
-                        rec.benchmark.bench,
-                        dataset.name,
-                        self.dataset_size,
-                        dataset_size,
-                        input.display()
-                    );
-                } else {
-                    for bench in dataset.result {
-                        let apps = self.match_apps(&rec.benchmark.bench, &rec.segul_version);
-                        let pubs = self.parse_pubs(&dataset.name);
-                        write!(writer, "{},", apps.name)?;
-                        write!(writer, "{},", apps.version)?;
-                        write!(writer, "{},", pubs.pubs.name)?;
-                        write!(
-                            writer,
-                            "\"{}\",",
-                            self.create_dataset_name(
-                                &pubs.pubs.name,
-                                &pubs.pubs.datatype,
-                                pubs.pubs.char_counts
-                            )
-                        )?;
-                        write!(writer, "{},", pubs.pubs.ntax)?;
-                        write!(writer, "{},", pubs.pubs.char_counts)?;
-                        write!(writer, "{},", pubs.pubs.aln_counts)?;
-                        write!(writer, "{},", pubs.pubs.site_counts)?;
-                        write!(writer, "{},", pubs.pubs.datatype)?;
-                        write!(writer, "{},", analysis_name)?;
-                        write!(
-                            writer,
-                            "{},",
-                            self.parse_platform_with_app_name(&rec.cpu, &apps.name)
-                        )?;
-                        write!(writer, "{},", self.parse_app_type(&apps.name))?;
-                        write!(writer, "{},", self.parse_os(&rec.os, &apps.name))?;
-                        write!(writer, "{},", rec.cpu)?;
-                        write!(writer, "{},", date)?;
-                        write!(writer, "TRUE,")?;
-                        write!(writer, "{},", bench.exec_time)?;
-                        write!(writer, "{},", bench.mem_usage)?;
-                        write!(writer, "{},", bench.cpu_usage.replace('%', ""))?;
-                        write!(writer, "{},", self.parse_time_to_secs(&bench.exec_time))?;
-                        write!(writer, "{}", self.convert_kb_to_mb(&bench.mem_usage))?;
-                        writeln!(writer)?;
-                    }
-                }
-            }
-        }
-        println!("Finished parsing {} as {}", input.display(), analysis_name);
+    /// Read every input with the given format.
+    /// By default, the format is detected for each file.
+    pub fn format(mut self, format: InputFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
 
-        Ok(())
+    /// Skip malformed dataset blocks instead of failing the whole file.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
     }
 
-    fn create_dataset_name(&self, pub_name: &str, datatype: &str, char_counts: usize) -> String {
-        let char_counts_mb = char_counts as f32 / 1_000_000.0;
-        format!("{} ({:.1} MBases, {})", pub_name, char_counts_mb, datatype)
+    /// Dataset registry used to match the dataset paths.
+    /// Defaults to the built-in registry.
+    pub fn datasets(mut self, datasets: DatasetRegistry) -> Self {
+        self.datasets = datasets;
+        self
     }
 
-    fn parse_platform_with_app_name(&self, cpu_model: &str, app: &str) -> String {
-        if self.is_linux_gui(app) {
-            return String::from("Desktop");
-        }
+    /// App registry used to match the benchmark lines.
+    /// Defaults to the built-in registry.
+    pub fn apps(mut self, apps: AppRegistry) -> Self {
+        self.apps = apps;
+        self
+    }
 
-        if app.contains("GUI") {
-            return String::from("Mobile");
-        }
+    /// Analysis registry used to name the analyses.
+    /// Defaults to the built-in registry.
+    pub fn analyses(mut self, analyses: AnalysisRegistry) -> Self {
+        self.analyses = analyses;
+        self
+    }
 
-        parse_platform(cpu_model)
+    /// Also write the summary statistics of each dataset
+    /// into `<output>.summary.csv`.
+    pub fn summarize(mut self, summarize: bool) -> Self {
+        self.summarize = summarize;
+        self
     }
 
-    fn parse_os(&self, os: &str, app: &str) -> String {
-        if app.contains("GUI") {
-            // capture the word inside the parenthesis
-            let name = app.split_whitespace().collect::<Vec<&str>>();
-            assert!(name.len() == 3, "Invalid app name {}", app);
-            // remove parenthesis and return the last word
-            let os = name[2].replace("(", "").replace(")", "");
-            return os;
-        }
+    /// Also fit how each app scales with the dataset size
+    /// into `<output>.scaling.csv`.
+    pub fn scaling(mut self, scaling: bool) -> Self {
+        self.scaling = scaling;
+        self
+    }
 
-        os.to_string()
+    /// Discard the first `replicates` of each dataset as warm-up runs.
+    pub fn discard_warmup(mut self, replicates: usize) -> Self {
+        self.warmup = replicates;
+        self
     }
 
-    fn is_linux_gui(&self, app: &str) -> bool {
-        app.to_lowercase().contains("linux") && app.contains("GUI")
+    /// Where to write the discarded warm-up replicates.
+    /// By default, they are dropped.
+    pub fn warmup_output(mut self, output: WarmupOutput) -> Self {
+        self.warmup_output = output;
+        self
     }
 
-    fn parse_app_type(&self, app: &str) -> String {
-        if app.contains("GUI") {
-            return String::from("GUI");
-        }
+    /// Flag the outliers of each dataset with the given method.
+    pub fn outliers(mut self, method: OutlierMethod) -> Self {
+        self.outliers = Some(method);
+        self
+    }
 
-        String::from("CLI")
+    /// Leave the flagged outliers out of the summary statistics.
+    pub fn exclude_outliers(mut self, exclude: bool) -> Self {
+        self.exclude_outliers = exclude;
+        self
     }
 
-    fn convert_kb_to_mb(&self, kb: &str) -> f32 {
-        kb.parse::<f32>().expect("Failed parsing kb to f64") / 1024.0
+    /// Also compare every app with the `app` baseline
+    /// into `<output>.baseline.csv`.
+    pub fn baseline(mut self, app: &str) -> Self {
+        self.baseline = Some(app.to_string());
+        self
     }
 
-    fn parse_time_to_secs(&self, exe_time: &str) -> f64 {
-        let splitted_time: Vec<&str> = exe_time.split(':').collect();
-        match splitted_time.len() {
-            1 => splitted_time[0].parse().expect("Failed parsing seconds"),
-            2 => {
-                splitted_time[0]
-                    .parse::<f64>()
-                    .expect("Failed parsing minutes")
-                    * 60.0
-                    + splitted_time[1]
-                        .parse::<f64>()
-                        .expect("Failed parsing seconds")
-            }
-            3 => {
-                splitted_time[0]
-                    .parse::<f64>()
-                    .expect("Failed parsing hours")
-                    * 3600.0
-                    + splitted_time[1]
-                        .parse::<f64>()
-                        .expect("Failed parsing minutes")
-                        * 60.0
-                    + splitted_time[2]
-                        .parse::<f64>()
-                        .expect("Failed parsing seconds")
-            }
-            _ => panic!("Invalid time format"),
-        }
+    /// Format of the output rows. Defaults to CSV.
+    pub fn output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = format;
+        self
     }
 
-    fn parse_analysis_name(&self, input: &'a str) -> &'a str {
-        input
-            .split('_')
-            .next()
-            .expect("Failed parsing analysis name")
+    /// Also write the rows into the SQLite database at `path`.
+    /// Rows already in the database are updated, not duplicated.
+    pub fn sqlite(mut self, path: &Path) -> Self {
+        self.sqlite = Some(path.to_path_buf());
+        self
     }
 
-    fn parse_pubs(&self, pubs: &str) -> PubRecord {
-        let mut pub_recs = PubRecord::new();
-        match pubs.to_lowercase() {
-            pubs if pubs.contains("esselstyn") => pub_recs.pub_esselstyn(),
-            pubs if pubs.contains("oliveros") => pub_recs.pub_oliveros(),
-            pubs if pubs.contains("jarvis") => pub_recs.pub_jarvis(),
-            pubs if pubs.contains("chan") => pub_recs.pub_chan(),
-            pubs if pubs.contains("wu") => pub_recs.pub_wu(),
-            pubs if pubs.contains("shen") => pub_recs.pub_shen(),
-            pubs if pubs.contains("SRR26062012") => pub_recs.pub_genome(),
-            _ => pub_recs.pub_unknown(pubs),
+    pub fn build(self) -> Parser<'a> {
+        Parser {
+            input: self.input,
+            output: self.output,
+            expected_replicates: self.expected_replicates,
+            format: self.format,
+            lenient: self.lenient,
+            datasets: self.datasets,
+            apps: self.apps,
+            analyses: self.analyses,
+            summarize: self.summarize,
+            scaling: self.scaling,
+            warmup: self.warmup,
+            warmup_output: self.warmup_output,
+            outliers: self.outliers,
+            exclude_outliers: self.exclude_outliers,
+            baseline: self.baseline,
+            output_format: self.output_format,
+            sqlite: self.sqlite,
         }
+    }
+}
 
-        pub_recs
-    }
-
-    fn match_apps(&self, app: &str, version: &str) -> Apps {
-        let mut apps = Apps::new();
-        match app {
-            app if app.contains("SEGUL") => {
-                if app.contains("ignore") {
-                    apps.name = String::from("SEGUL CLI (--datatype ignore)");
-                } else if app.contains("GUI") {
-                    let name = app.split_whitespace().collect::<Vec<&str>>();
-                    assert!(name.len() == 4, "Invalid app name {}", app);
-                    apps.name = format!("SEGUL GUI ({})", name[3]);
-                } else if app.contains("API") {
-                    apps.name = String::from("SEGUL API");
-                } else {
-                    apps.name = String::from("SEGUL CLI");
+impl<'a> Parser<'a> {
+    pub fn builder(input: &'a [PathBuf], output: &'a Path) -> ParserBuilder<'a> {
+        ParserBuilder::new(input, output)
+    }
+
+    /// Parses every input file and writes the rows into the output file
+    /// with the extension of the output format, e.g. `<output>.parquet`.
+    /// The rows are also written into the SQLite database if one is set,
+    /// and the reports that are enabled into their own CSV files.
+    ///
+    /// Files that fail to parse are skipped and their errors are collected
+    /// in the returned summary. In lenient mode, only the malformed dataset
+    /// blocks are skipped and reported as warnings. Only failures to write
+    /// the output are fatal.
+    pub fn parse_benchmark(&self) -> Result<ParseSummary, BenchParseError> {
+        let mut writer = self.write_records("")?;
+        let warmup_writer = match self.warmup_output {
+            WarmupOutput::File if self.warmup > 0 => Some(self.write_records("warmup")?),
+            _ => None,
+        };
+        let (rows, summary) = self.read_rows();
+        let keep_warmup = self.warmup_output == WarmupOutput::Flag;
+        let output: Vec<&BenchRow> = rows.iter().filter(|r| !r.warmup || keep_warmup).collect();
+        writer.write_rows(&output)?;
+        writer.finish()?;
+        if let Some(mut warmup_writer) = warmup_writer {
+            let warmup: Vec<&BenchRow> = rows.iter().filter(|r| r.warmup).collect();
+            warmup_writer.write_rows(&warmup)?;
+            warmup_writer.finish()?;
+        }
+        if let Some(path) = &self.sqlite {
+            self.write_sqlite(path, &rows)?;
+        }
+        if self.summarize {
+            self.write_summary(&rows)?;
+        }
+        if self.scaling {
+            self.write_scaling(&rows)?;
+        }
+        if let Some(baseline) = &self.baseline {
+            self.write_baseline(&rows, baseline)?;
+        }
+        if self.lenient {
+            self.write_warnings(&summary.warnings)?;
+        }
+        Ok(summary)
+    }
+
+    /// Parses every input file into rows without writing any output.
+    /// The errors are collected in the summary as in [`Parser::parse_benchmark`].
+    pub fn read_rows(&self) -> (Vec<BenchRow>, ParseSummary) {
+        self.print_input();
+        let mut summary = ParseSummary::default();
+        let mut rows = Vec::new();
+        for file in self.input {
+            let records = match self.read_file(file, &mut summary.warnings) {
+                Ok(records) => records,
+                Err(e) => {
+                    summary.errors.push(e.with_path(file));
+                    continue;
                 }
-                apps.version = format!("v{}", version);
-            }
-            app if app.contains("AMAS") => {
-                if app.contains("align") {
-                    apps.name = String::from("AMAS (--check-align)");
-                } else if app.contains("--remove-empty") {
-                    apps.name = String::from("AMAS (--remove-empty)");
-                } else {
-                    apps.name = String::from("AMAS");
+            };
+            let file_rows = match self.create_rows(file, &records) {
+                Ok(file_rows) => file_rows,
+                Err(e) => {
+                    summary.errors.push(e.with_path(file));
+                    continue;
                 }
-                apps.version = String::from("v1.02");
-            }
-            app if app.contains("Phyluce") => {
-                apps.name = String::from("Phyluce");
-                apps.version = String::from("v1.7.3");
+            };
+            if file_rows.is_empty() {
+                summary.skipped.push(file.to_path_buf());
+                continue;
             }
-            app if app.contains("goalign") => {
-                if app.contains("multi-core") {
-                    apps.name = String::from("goalign (multi-core)");
-                } else {
-                    apps.name = String::from("goalign (single-core)");
+            // A file may have more than one `Analysis:` line.
+            let mut analyses: Vec<&str> = Vec::new();
+            for row in &file_rows {
+                if !row.analysis.is_empty() && !analyses.contains(&row.analysis.as_str()) {
+                    analyses.push(&row.analysis);
                 }
             }
-            _ => {
-                apps.name = String::from(app);
-                apps.version = String::from("Unknown");
+            if analyses.is_empty() {
+                println!("Finished parsing {}", file.display());
+            } else {
+                println!(
+                    "Finished parsing {} as {}",
+                    file.display(),
+                    analyses.join(", ")
+                );
             }
-        };
-        apps
-    }
-
-    fn match_analyses(&self, analysis: &str) -> String {
-        let dataset_format = self.parse_dataset_format(analysis);
-        let analysis = match dataset_format.0.as_str() {
-            "concat" => "Alignment Concatenation".to_string(),
-            "convert" => "Alignment Conversion".to_string(),
-            "summary" => "Alignment Summary".to_string(),
-            "remove" => "Sequence Removal".to_string(),
-            "split" => "Alignment Splitting".to_string(),
-            "raw" => "Read Summary".to_string(),
-            _ => analysis.to_string(),
-        };
-        let dataset = dataset_format.1;
-        match dataset {
-            Some(dataset) => format!("{} ({})", analysis, dataset.to_uppercase()),
-            None => format!("{} (NEXUS)", analysis),
+            rows.extend(file_rows);
+            summary.parsed.push(file.to_path_buf());
         }
-    }
-
-    fn parse_dataset_format(&self, dataset: &str) -> (String, Option<String>) {
-        if dataset.contains('-') {
-            let dataset = dataset.split('-').collect::<Vec<&str>>();
-            (dataset[0].to_string(), Some(dataset[1].to_string()))
+        (rows, summary)
+    }
+
+    /// Parses every input file and tests `first` against `second`
+    /// in each dataset group. The selectors must not overlap. The table is written into `<output>.csv`,
+    /// or `<output>.md` if `markdown` is set.
+    pub fn compare(
+        &self,
+        first: &AppSelector,
+        second: &AppSelector,
+        method: TestMethod,
+        markdown: bool,
+    ) -> Result<ParseSummary, BenchParseError> {
+        if first.overlaps(second) {
+            return Err(BenchParseError::new(ErrorReason::OverlappingApps {
+                first: first.to_string(),
+                second: second.to_string(),
+            }));
+        }
+        let (rows, summary) = self.read_rows();
+        let tests = compare::compare_apps(&rows, first, second, method, self.exclude_outliers);
+        let output = self
+            .output
+            .with_extension(if markdown { "md" } else { "csv" });
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut writer = BufWriter::new(File::create(output)?);
+        if markdown {
+            compare::write_markdown(&mut writer, &tests, first, second)?;
         } else {
-            (dataset.to_string(), None)
+            compare::write_csv(&mut writer, &tests, first, second)?;
+        }
+        writer.flush()?;
+        if self.lenient {
+            self.write_warnings(&summary.warnings)?;
         }
+        Ok(summary)
     }
 
-    fn print_input(&self) {
-        println!("File Counts: {}", self.input.len());
+    fn write_warnings(&self, warnings: &[BenchParseError]) -> Result<(), BenchParseError> {
+        let output = self.output.with_extension("warnings.txt");
+        let mut writer = BufWriter::new(File::create(output)?);
+        for warning in warnings {
+            writeln!(writer, "{}", warning)?;
+        }
+        writer.flush()?;
+        Ok(())
     }
-}
 
-struct PubRecord {
-    pubs: Pubs,
-}
+    fn write_summary(&self, rows: &[BenchRow]) -> Result<(), BenchParseError> {
+        let output = self.output.with_extension("summary.csv");
+        let mut writer = BufWriter::new(File::create(output)?);
+        let summaries = summary::summarize(rows, self.exclude_outliers);
+        summary::write_summary(&mut writer, &summaries)?;
+        writer.flush()?;
+        Ok(())
+    }
 
-impl PubRecord {
-    fn new() -> Self {
-        Self { pubs: Pubs::new() }
-    }
-
-    fn pub_chan(&mut self) {
-        self.pubs.name = String::from("Chan et al. 2020");
-        self.pubs.ntax = 50;
-        self.pubs.aln_counts = 13181;
-        self.pubs.char_counts = 239310808;
-        self.pubs.site_counts = 6180393;
-        self.pubs.datatype = String::from("DNA");
-    }
-
-    fn pub_esselstyn(&mut self) {
-        self.pubs.name = String::from("Esselstyn et al. 2021");
-        self.pubs.ntax = 102;
-        self.pubs.aln_counts = 4040;
-        self.pubs.char_counts = 358099656;
-        self.pubs.site_counts = 5398947;
-        self.pubs.datatype = String::from("DNA");
-    }
-
-    fn pub_jarvis(&mut self) {
-        self.pubs.name = String::from("Jarvis et al. 2014");
-        self.pubs.ntax = 49;
-        self.pubs.aln_counts = 3679;
-        self.pubs.char_counts = 453333006;
-        self.pubs.site_counts = 9251694;
-        self.pubs.datatype = String::from("DNA");
-    }
-
-    fn pub_oliveros(&mut self) {
-        self.pubs.name = String::from("Oliveros et al. 2019");
-        self.pubs.ntax = 221;
-        self.pubs.aln_counts = 4060;
-        self.pubs.char_counts = 522529858;
-        self.pubs.site_counts = 2464926;
-        self.pubs.datatype = String::from("DNA");
-    }
-
-    fn pub_shen(&mut self) {
-        self.pubs.name = String::from("Shen et al. 2018");
-        self.pubs.ntax = 343;
-        self.pubs.aln_counts = 2408;
-        self.pubs.char_counts = 398842115;
-        self.pubs.site_counts = 1162805;
-        self.pubs.datatype = String::from("AA");
-    }
-
-    fn pub_wu(&mut self) {
-        self.pubs.name = String::from("Wu et al. 2018");
-        self.pubs.ntax = 90;
-        self.pubs.aln_counts = 5162;
-        self.pubs.char_counts = 257060172;
-        self.pubs.site_counts = 3050198;
-        self.pubs.datatype = String::from("AA");
-    }
-
-    fn pub_genome(&mut self) {
-        self.pubs.name = String::from("SRR26062012");
-        self.pubs.ntax = 1;
-        self.pubs.aln_counts = 0;
-        self.pubs.char_counts = 243874896842;
-        self.pubs.site_counts = 0;
-        self.pubs.datatype = String::from("DNA");
-    }
-
-    fn pub_unknown(&mut self, pubs: &str) {
-        self.pubs.name = pubs.to_string();
-        self.pubs.ntax = 0;
-        self.pubs.aln_counts = 0;
-        self.pubs.site_counts = 0;
-        self.pubs.datatype = String::from("Whole Genome");
+    fn write_sqlite(&self, path: &Path, rows: &[BenchRow]) -> Result<(), BenchParseError> {
+        let rows = rows
+            .iter()
+            .filter(|r| !r.warmup || self.warmup_output != WarmupOutput::Drop);
+        sqlite::write_rows(path, rows).map_err(|e| e.with_path(path))
     }
-}
 
-struct BenchReader<R: Read> {
-    reader: BufReader<R>,
-    cpu: String,
-    os: String,
-    bench_name: String,
-    segul_version: String,
-    dataset: Dataset,
-    lcounts: usize,
-    dataset_size: usize,
-}
+    fn write_scaling(&self, rows: &[BenchRow]) -> Result<(), BenchParseError> {
+        let output = self.output.with_extension("scaling.csv");
+        let mut writer = BufWriter::new(File::create(output)?);
+        let scaling = scaling::fit_scaling(rows, self.exclude_outliers);
+        scaling::write_scaling(&mut writer, &scaling)?;
+        writer.flush()?;
+        Ok(())
+    }
 
-impl<R: Read> BenchReader<R> {
-    fn new(reader: R, dataset_size: usize) -> Self {
-        Self {
-            reader: BufReader::new(reader),
-            cpu: String::new(),
-            os: String::new(),
-            bench_name: String::new(),
-            segul_version: String::new(),
-            dataset: Dataset::new(),
-            lcounts: 0,
-            dataset_size: dataset_size,
-        }
+    fn write_baseline(&self, rows: &[BenchRow], baseline: &str) -> Result<(), BenchParseError> {
+        let output = self.output.with_extension("baseline.csv");
+        let mut writer = BufWriter::new(File::create(output)?);
+        let comparisons = baseline::compare_to_baseline(rows, baseline, self.exclude_outliers);
+        baseline::write_comparisons(&mut writer, &comparisons)?;
+        writer.flush()?;
+        Ok(())
     }
 
-    fn next_record(&mut self) -> Option<Records> {
-        while let Some(Ok(line)) = self.reader.by_ref().lines().next() {
-            self.match_line_keyword(&line);
-            if self.lcounts >= 1 {
-                self.lcounts += 1;
-                let mut bench = BenchmarkResult::new();
-                let bench_result = line.split_whitespace().collect::<Vec<&str>>();
-                bench.exec_time = bench_result[0].to_string();
-                bench.mem_usage = bench_result[1].to_string();
-                bench.cpu_usage = bench_result[2].to_string();
-                self.dataset.result.push(bench);
+    // Output of the rows, with an optional suffix before the extension.
+    fn write_records(&self, suffix: &str) -> Result<RowWriter<BufWriter<File>>, BenchParseError> {
+        let extension = self.output_format.extension();
+        let output = match suffix {
+            "" => self.output.with_extension(extension),
+            suffix => self
+                .output
+                .with_extension(format!("{}.{}", suffix, extension)),
+        };
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        RowWriter::create(&output, self.output_format)
+    }
+
+    fn read_file(
+        &self,
+        input: &Path,
+        warnings: &mut Vec<BenchParseError>,
+    ) -> Result<Vec<Records>, BenchParseError> {
+        parse_file_stem(input)?;
+        let mut file = File::open(input)?;
+        let format = match self.format {
+            Some(format) => format,
+            None => {
+                let format = detect_format(BufReader::new(&file)).map_err(BenchParseError::new)?;
+                file.rewind()?;
+                format
             }
-
-            if !self.bench_name.is_empty() {
-                if line.starts_with("Dataset") {
-                    self.lcounts = 1;
-                    self.dataset.name = self.capture_name(&line);
+        };
+        if format == InputFormat::Hyperfine {
+            return self.read_hyperfine(file, input, warnings);
+        }
+        let mut reader = BenchReader::new(file).format(format);
+        if let Some(replicates) = self.expected_replicates {
+            reader = reader.expect_replicates(replicates);
+        }
+        if !self.lenient {
+            return reader.collect();
+        }
+        let mut records = Vec::new();
+        for rec in reader {
+            match rec {
+                Ok(rec) => records.push(rec),
+                Err(e) if matches!(e.reason, ErrorReason::Io(_)) => return Err(e),
+                Err(e) => warnings.push(e.with_path(input)),
+            }
+        }
+        Ok(records)
+    }
+
+    fn read_hyperfine(
+        &self,
+        file: File,
+        input: &Path,
+        warnings: &mut Vec<BenchParseError>,
+    ) -> Result<Vec<Records>, BenchParseError> {
+        let mut records = hyperfine::read_records(file)?;
+        if let Some(expected) = self.expected_replicates {
+            let mut valid = Vec::with_capacity(records.len());
+            for rec in records {
+                let found = rec.benchmark.dataset[0].result.len();
+                if found == expected {
+                    valid.push(rec);
+                    continue;
                 }
-
-                if self.lcounts > self.dataset_size && !line.trim().is_empty() {
-                    let recs = self.parse_records();
-                    self.lcounts = 0;
-                    return Some(recs);
+                let err = BenchParseError {
+                    path: None,
+                    line: None,
+                    text: Some(rec.benchmark.bench.clone()),
+                    reason: ErrorReason::InvalidReplicateCount { expected, found },
+                };
+                if !self.lenient {
+                    return Err(err);
                 }
+                warnings.push(err.with_path(input));
             }
+            records = valid;
         }
+        Ok(records)
+    }
 
-        if self.dataset.has_record() {
-            let recs = self.parse_records();
-            self.bench_name.clear();
-            Some(recs)
-        } else {
-            None
+    fn create_rows(
+        &self,
+        input: &Path,
+        records: &[Records],
+    ) -> Result<Vec<BenchRow>, BenchParseError> {
+        let file_stem = parse_file_stem(input)?;
+        let analysis = self.parse_analysis_name(file_stem);
+        let file_analysis = self.analyses.match_analysis(analysis);
+        let date = parse_date(file_stem);
+        let mut rows = Vec::new();
+        for rec in records {
+            // The `Analysis:` line takes precedence over the file name.
+            let analysis_name = if rec.analysis.is_empty() {
+                file_analysis.clone()
+            } else {
+                self.analyses.match_analysis(&rec.analysis)
+            };
+            for dataset in &rec.benchmark.dataset {
+                let block_start = rows.len();
+                for (i, bench) in dataset.result.iter().enumerate() {
+                    let apps = self
+                        .apps
+                        .match_app(&rec.benchmark.bench, &rec.segul_version);
+                    let pubs = self.datasets.match_dataset(&dataset.name);
+                    let mut row = BenchRow::new();
+                    row.exec_time_secs = parse_time_to_secs(&bench.exec_time)
+                        .map_err(|e| BenchParseError::new(e).with_path(input))?;
+                    // Memory and CPU usage are optional in hyperfine exports.
+                    if !bench.mem_usage.is_empty() {
+                        row.ram_usage_kb = Some(
+                            bench
+                                .mem_usage
+                                .parse()
+                                .map_err(|_| BenchParseError::new(ErrorReason::InvalidMemory))?,
+                        );
+                        row.ram_usage_mb = Some(
+                            convert_kb_to_mb(&bench.mem_usage)
+                                .map_err(|e| BenchParseError::new(e).with_path(input))?,
+                        );
+                    }
+                    if !bench.cpu_usage.is_empty() {
+                        row.cpu_usage = Some(
+                            parse_cpu_usage(&bench.cpu_usage)
+                                .map_err(|e| BenchParseError::new(e).with_path(input))?,
+                        );
+                    }
+                    row.threads = rec.machine.logical_cpus();
+                    // Share of the logical CPUs, so 100% means every CPU was busy.
+                    row.cpu_usage_normalized = row
+                        .cpu_usage
+                        .zip(row.threads)
+                        .map(|(usage, cpus)| usage / cpus as f64);
+                    row.dataset =
+                        self.create_dataset_name(&pubs.name, &pubs.datatype, pubs.char_counts);
+                    row.platform = self.parse_platform_with_app(&rec.machine.cpu, &apps);
+                    row.app_type = apps.app_type.to_string();
+                    row.os = apps.os.unwrap_or_else(|| rec.machine.os.clone());
+                    row.app = apps.name;
+                    row.version = apps.version;
+                    row.pubs = pubs.name;
+                    row.ntax = pubs.ntax;
+                    row.char_counts = pubs.char_counts;
+                    row.aln_counts = pubs.aln_counts;
+                    row.site_counts = pubs.site_counts;
+                    row.datatype = pubs.datatype;
+                    row.analysis = analysis_name.clone();
+                    row.cpu = rec.machine.cpu.clone();
+                    row.date = date.clone();
+                    row.latest_bench = true;
+                    row.exec_time = bench.exec_time.clone();
+                    row.replicates = self.count_replicates(dataset.result.len());
+                    row.replicate = i + 1;
+                    row.bench = rec.benchmark.bench.clone();
+                    row.bench_line = rec.benchmark.line;
+                    row.warmup = i < self.warmup;
+                    row.user_time_secs = bench.user_time;
+                    row.system_time_secs = bench.system_time;
+                    row.major_page_faults = bench.major_page_faults;
+                    row.minor_page_faults = bench.minor_page_faults;
+                    row.voluntary_switches = bench.voluntary_switches;
+                    row.involuntary_switches = bench.involuntary_switches;
+                    row.fs_inputs = bench.fs_inputs;
+                    row.fs_outputs = bench.fs_outputs;
+                    row.exit_status = bench.exit_status;
+                    row.instructions_retired = bench.instructions_retired;
+                    row.peak_memory_kb = bench.peak_memory_kb;
+                    row.cores = rec.machine.physical_cores();
+                    row.total_ram_kb = rec.machine.total_ram_kb;
+                    row.kernel = rec.machine.kernel.clone();
+                    rows.push(row);
+                }
+                if let Some(method) = self.outliers {
+                    outlier::flag_outliers(&mut rows[block_start..], method);
+                }
+            }
         }
+        Ok(rows)
     }
 
-    fn match_line_keyword(&mut self, line: &str) {
-        match line {
-            line if line.starts_with("Model") => {
-                self.cpu = self.capture_name(line);
-                self.os = String::from("Linux");
-            }
-            line if line.starts_with("Darwin") => {
-                self.cpu = String::from("Apple M1");
-                self.os = String::from("macOS");
-            }
-            line if line.contains("Microsoft") => self.os = String::from("Windows (WSL)"),
-            line if line.contains("X86_64") => {
-                self.os = String::from("macOS (Mb Air)");
-                self.cpu = String::from("Intel Core i5-4260U")
-            }
-            line if line.starts_with("Benchmarking") => {
-                self.bench_name = line.to_string();
-            }
-            line if line.starts_with("segul") => {
-                self.segul_version = line.split_whitespace().nth(1).unwrap().to_string();
-            }
-            _ => (),
+    // Replicates of a dataset in the output. The warm-up replicates
+    // are counted only if they are kept in it.
+    fn count_replicates(&self, replicates: usize) -> usize {
+        match self.warmup_output {
+            WarmupOutput::Flag => replicates,
+            _ => replicates.saturating_sub(self.warmup),
         }
     }
 
-    fn capture_name(&self, line: &str) -> String {
-        line.split(':')
-            .nth(1)
-            .expect("File capturing name")
-            .trim()
-            .to_string()
+    fn create_dataset_name(&self, pub_name: &str, datatype: &str, char_counts: usize) -> String {
+        let char_counts_mb = char_counts as f32 / 1_000_000.0;
+        format!("{} ({:.1} MBases, {})", pub_name, char_counts_mb, datatype)
     }
 
-    fn parse_records(&mut self) -> Records {
-        let mut recs = Records::new();
-        recs.cpu = self.cpu.clone();
-        recs.os = self.os.clone();
-        recs.segul_version = self.segul_version.clone();
-        let mut bench = Benchmark::new();
-        bench.bench = self.bench_name.clone();
-        bench.dataset.push(self.dataset.clone());
-        recs.benchmark = bench;
-        self.dataset.clear();
+    fn parse_platform_with_app(&self, cpu_model: &str, apps: &Apps) -> String {
+        if apps.app_type == AppType::Gui {
+            let os = apps.os.as_deref().unwrap_or_default();
+            if os.to_lowercase().contains("linux") {
+                return String::from("Desktop");
+            }
+            return String::from("Mobile");
+        }
 
-        recs
+        parse_platform(cpu_model)
     }
-}
 
-impl<R: Read> Iterator for BenchReader<R> {
-    type Item = Records;
+    fn parse_analysis_name(&self, input: &'a str) -> &'a str {
+        input
+            .split('_')
+            .next()
+            .expect("Failed parsing analysis name")
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.next_record()
+    fn print_input(&self) {
+        println!("File Counts: {}", self.input.len());
     }
 }
 
@@ -510,6 +655,58 @@ fn parse_date(file_stem: &str) -> String {
     }
 }
 
+fn parse_file_stem(input: &Path) -> Result<&str, BenchParseError> {
+    input
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| BenchParseError::new(ErrorReason::InvalidFileName).with_path(input))
+}
+
+pub(crate) fn parse_time_to_secs(exe_time: &str) -> Result<f64, ErrorReason> {
+    let parse = |t: &str| t.parse::<f64>().map_err(|_| ErrorReason::InvalidTime);
+    let splitted_time: Vec<&str> = exe_time.split(':').collect();
+    match splitted_time.len() {
+        1 => parse(splitted_time[0]),
+        2 => Ok(parse(splitted_time[0])? * 60.0 + parse(splitted_time[1])?),
+        3 => Ok(parse(splitted_time[0])? * 3600.0
+            + parse(splitted_time[1])? * 60.0
+            + parse(splitted_time[2])?),
+        _ => Err(ErrorReason::InvalidTime),
+    }
+}
+
+// Matches the GNU `%E` format: [h:]mm:ss.ss
+pub(crate) fn format_elapsed(secs: f64) -> String {
+    let hours = (secs / 3600.0).floor();
+    let minutes = ((secs - hours * 3600.0) / 60.0).floor();
+    let seconds = secs - hours * 3600.0 - minutes * 60.0;
+    if hours > 0.0 {
+        format!("{}:{:02}:{:05.2}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:05.2}", minutes, seconds)
+    }
+}
+
+pub(crate) fn convert_kb_to_mb(kb: &str) -> Result<f32, ErrorReason> {
+    kb.parse::<f32>()
+        .map(|kb| kb / 1024.0)
+        .map_err(|_| ErrorReason::InvalidMemory)
+}
+
+pub(crate) fn parse_cpu_usage(cpu_usage: &str) -> Result<f64, ErrorReason> {
+    cpu_usage
+        .trim_end_matches('%')
+        .parse::<f64>()
+        .map_err(|_| ErrorReason::InvalidCpuUsage)
+}
+
+pub(crate) fn validate_result(bench: &BenchmarkResult) -> Result<(), ErrorReason> {
+    parse_time_to_secs(&bench.exec_time)?;
+    convert_kb_to_mb(&bench.mem_usage)?;
+    parse_cpu_usage(&bench.cpu_usage)?;
+    Ok(())
+}
+
 fn parse_platform(cpu_model: &str) -> String {
     lazy_static! {
         static ref RE: Regex = Regex::new(r"i(\d{1})-(\d{4}U)").expect("Failed to compile regex");
@@ -529,7 +726,7 @@ mod tests {
     macro_rules! initialize_parser {
         ($parser: ident) => {
             let path = [PathBuf::from(".")];
-            let $parser = Parser::new(&path, Path::new("results.csv"), 5);
+            let $parser = Parser::builder(&path, Path::new("results.csv")).build();
         };
     }
 
@@ -548,12 +745,18 @@ mod tests {
     }
 
     #[test]
-    fn parse_time_to_secs() {
-        initialize_parser!(parser);
-        let time = parser.parse_time_to_secs("00:42.0");
-        let time_minute = parser.parse_time_to_secs("01:30.00");
+    fn test_parse_time_to_secs() {
+        let time = parse_time_to_secs("00:42.0").unwrap();
+        let time_minute = parse_time_to_secs("01:30.00").unwrap();
         assert_eq!(time, 42.0);
         assert_eq!(time_minute, 90.0);
+        assert!(parse_time_to_secs("1:2:3:4").is_err());
+    }
+
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!("1:30.25", format_elapsed(90.25));
+        assert_eq!("1:02:05.50", format_elapsed(3725.5));
     }
 
     #[test]
@@ -561,7 +764,48 @@ mod tests {
         initialize_parser!(parser);
         let file_name = "remove_bench_raw_aa_OpenSUSE_2022-10-04.txt";
         let name = parser.parse_analysis_name(file_name);
-        assert_eq!("Sequence Removal (NEXUS)", parser.match_analyses(name));
+        assert_eq!(
+            "Sequence Removal (NEXUS)",
+            parser.analyses.match_analysis(name)
+        );
+    }
+
+    #[test]
+    fn test_replicates_without_warmup() {
+        let files = [PathBuf::from(
+            "tests/data/concat_bench_raw_OpenSUSE_2022-10-04.txt",
+        )];
+        let output = Path::new("results.csv");
+        let count = |warmup_output: WarmupOutput| {
+            let parser = Parser::builder(&files, output)
+                .discard_warmup(1)
+                .warmup_output(warmup_output)
+                .build();
+            let (rows, _) = parser.read_rows();
+            (
+                rows[0].replicates,
+                rows.iter().filter(|r| !r.warmup).count(),
+            )
+        };
+        let (replicates, kept) = count(WarmupOutput::Drop);
+        assert_eq!(9, replicates);
+        assert_eq!(0, kept % replicates);
+        assert_eq!(10, count(WarmupOutput::Flag).0);
+    }
+
+    #[test]
+    fn test_skip_empty_file() {
+        let dir = std::env::temp_dir().join(format!("bench-parser-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("concat_bench_raw_OpenSUSE_2022-10-04.json");
+        fs::write(&file, r#"{"results": []}"#).unwrap();
+        let files = [file];
+        let parser = Parser::builder(&files, Path::new("results.csv")).build();
+        let (rows, summary) = parser.read_rows();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(rows.is_empty());
+        assert!(summary.parsed.is_empty());
+        assert_eq!(files.to_vec(), summary.skipped);
     }
 
     #[test]
@@ -571,11 +815,11 @@ mod tests {
             .expect("Failed to read glob pattern")
             .filter_map(|ok| ok.ok())
             .collect();
-        let parser = Parser::new(&files, Path::new("results.csv"), 5);
+        let parser = Parser::builder(&files, Path::new("results.csv")).build();
         let mut analysis = files
             .iter()
             .map(|f| parser.parse_analysis_name(f.file_name().unwrap().to_str().unwrap()))
-            .map(|n| parser.match_analyses(n))
+            .map(|n| parser.analyses.match_analysis(n))
             .collect::<Vec<_>>();
         analysis.dedup();
         assert_eq!(5, analysis.len());
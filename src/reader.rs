@@ -0,0 +1,520 @@
+use std::collections::VecDeque;
+use std::io::{prelude::*, BufReader};
+
+use crate::bsd;
+use crate::error::{BenchParseError, ErrorReason};
+use crate::format::InputFormat;
+use crate::gnu;
+use crate::machine::{self, Header};
+use crate::parser::validate_result;
+use crate::types::{Benchmark, BenchmarkResult, Dataset, MachineInfo, Records};
+
+/// Iterator over the dataset blocks of a raw benchmark log.
+///
+/// Each item holds the machine information, the benchmark name,
+/// and a single dataset with its replicate results. A dataset block
+/// ends at the next `Dataset`/`Benchmarking` header or blank line.
+/// Malformed lines are returned as errors carrying the line number.
+pub struct BenchReader<R: Read> {
+    reader: BufReader<R>,
+    header: Header,
+    machine: MachineInfo,
+    bench_name: String,
+    bench_line: usize,
+    segul_version: String,
+    analysis: String,
+    dataset: Dataset,
+    in_dataset: bool,
+    verbose_result: Option<BenchmarkResult>,
+    bsd_result: Option<BenchmarkResult>,
+    bsd_line: usize,
+    expected_replicates: Option<usize>,
+    format: Option<InputFormat>,
+    line_num: usize,
+    dataset_line: usize,
+    parsed: VecDeque<Result<Records, BenchParseError>>,
+}
+
+impl<R: Read> BenchReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            header: Header::new(),
+            machine: MachineInfo::new(),
+            bench_name: String::new(),
+            bench_line: 0,
+            segul_version: String::new(),
+            analysis: String::new(),
+            dataset: Dataset::new(),
+            in_dataset: false,
+            verbose_result: None,
+            bsd_result: None,
+            bsd_line: 0,
+            expected_replicates: None,
+            format: None,
+            line_num: 0,
+            dataset_line: 0,
+            parsed: VecDeque::new(),
+        }
+    }
+
+    /// Validate that every dataset has exactly `replicates` results.
+    pub fn expect_replicates(mut self, replicates: usize) -> Self {
+        self.expected_replicates = Some(replicates);
+        self
+    }
+
+    /// Only accept the result lines of the given format.
+    /// By default, the format is matched for each line.
+    pub fn format(mut self, format: InputFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    fn next_record(&mut self) -> Option<Result<Records, BenchParseError>> {
+        while self.parsed.is_empty() {
+            let line = match self.reader.by_ref().lines().next() {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => return Some(Err(e.into())),
+                None => break,
+            };
+            self.parse_line(&line);
+        }
+
+        if self.parsed.is_empty() && self.in_dataset {
+            let recs = self.finish_dataset();
+            self.parsed.push_back(recs);
+        }
+
+        self.parsed.pop_front()
+    }
+
+    fn parse_line(&mut self, line: &str) {
+        self.line_num += 1;
+        if self.in_dataset && self.is_block_boundary(line) {
+            let recs = self.finish_dataset();
+            self.parsed.push_back(recs);
+        }
+
+        if let Err(e) = self.match_line_keyword(line) {
+            self.parsed.push_back(Err(e));
+            return;
+        }
+
+        if self.in_dataset {
+            if let Err(e) = self.parse_result_line(line) {
+                // Skip the rest of the block until the next header.
+                self.in_dataset = false;
+                self.verbose_result = None;
+                self.bsd_result = None;
+                self.dataset.clear();
+                self.parsed.push_back(Err(e));
+            }
+        } else if !self.bench_name.is_empty() && line.starts_with("Dataset") {
+            match self.capture_name(line) {
+                Ok(name) => {
+                    self.in_dataset = true;
+                    self.dataset_line = self.line_num;
+                    self.dataset.name = name;
+                }
+                Err(e) => self.parsed.push_back(Err(e)),
+            }
+        }
+    }
+
+    fn finish_dataset(&mut self) -> Result<Records, BenchParseError> {
+        self.in_dataset = false;
+        if let Err(e) = self.push_bsd_result() {
+            self.verbose_result = None;
+            self.dataset.clear();
+            return Err(e);
+        }
+        let found = self.dataset.result.len();
+        let incomplete = self.verbose_result.take().is_some();
+        let reason = match self.expected_replicates {
+            _ if incomplete => Some(ErrorReason::IncompleteResult),
+            _ if found == 0 => Some(ErrorReason::EmptyDataset),
+            Some(expected) if expected != found => {
+                Some(ErrorReason::InvalidReplicateCount { expected, found })
+            }
+            _ => None,
+        };
+        match reason {
+            Some(reason) => {
+                let err = BenchParseError::at_line(reason, self.dataset_line, &self.dataset.name);
+                self.dataset.clear();
+                Err(err)
+            }
+            None => Ok(self.parse_records()),
+        }
+    }
+
+    fn match_line_keyword(&mut self, line: &str) -> Result<(), BenchParseError> {
+        // The structured header takes precedence over the cues below.
+        if machine::is_header_line(line) {
+            return machine::parse_header_line(&mut self.header, line)
+                .map_err(|e| self.line_error(e, line));
+        }
+        let is_fallback = machine::parse_fallback_line(&mut self.machine, line)
+            .map_err(|e| self.line_error(e, line))?;
+        // The lscpu output comes before the first benchmark.
+        if !is_fallback && self.bench_name.is_empty() {
+            machine::parse_lscpu_line(&mut self.machine, line);
+        }
+        match line {
+            line if line.starts_with("Model name") => {
+                self.machine.cpu = self.capture_name(line)?;
+                self.machine.os = String::from("Linux");
+            }
+            // Intel Macs also report Darwin, so this goes first.
+            line if line.contains("X86_64") => {
+                self.machine.os = String::from("macOS (Mb Air)");
+                self.machine.cpu = String::from("Intel Core i5-4260U");
+            }
+            line if line.starts_with("Darwin") => {
+                self.machine.cpu = String::from("Apple M1");
+                self.machine.os = String::from("macOS");
+            }
+            line if line.contains("Microsoft") => self.machine.os = String::from("Windows (WSL)"),
+            line if line.starts_with("Benchmarking") => {
+                self.bench_name = line.to_string();
+                self.bench_line = self.line_num;
+            }
+            line if line.starts_with("Analysis:") => {
+                self.analysis = self.capture_name(line)?;
+            }
+            line if line.starts_with("segul") => {
+                self.segul_version = line
+                    .split_whitespace()
+                    .nth(1)
+                    .ok_or_else(|| self.line_error(ErrorReason::MissingVersion, line))?
+                    .to_string();
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn parse_result_line(&mut self, line: &str) -> Result<(), BenchParseError> {
+        let format = match self.format {
+            Some(InputFormat::Hyperfine) | None => self.match_line_format(line),
+            Some(format) => format,
+        };
+        match format {
+            InputFormat::Bsd => self.parse_bsd_line(line),
+            InputFormat::GnuVerbose => self.parse_verbose_line(line),
+            _ => {
+                let bench = gnu::parse_compact_line(line).map_err(|e| self.line_error(e, line))?;
+                self.dataset.result.push(bench);
+                Ok(())
+            }
+        }
+    }
+
+    fn match_line_format(&self, line: &str) -> InputFormat {
+        if self.bsd_result.is_some() || bsd::is_time_line(line) {
+            InputFormat::Bsd
+        } else if self.verbose_result.is_some() || gnu::is_verbose_start(line) {
+            InputFormat::GnuVerbose
+        } else {
+            InputFormat::Gnu
+        }
+    }
+
+    fn parse_bsd_line(&mut self, line: &str) -> Result<(), BenchParseError> {
+        if bsd::is_time_line(line) {
+            self.push_bsd_result()?;
+            let bench = bsd::parse_time_line(line).map_err(|e| self.line_error(e, line))?;
+            self.bsd_result = Some(bench);
+            self.bsd_line = self.line_num;
+            return Ok(());
+        }
+        match self.bsd_result.as_mut() {
+            Some(bench) => {
+                bsd::parse_resource_line(bench, line).map_err(|e| self.line_error(e, line))
+            }
+            None => Err(self.line_error(ErrorReason::UnexpectedLine(InputFormat::Bsd), line)),
+        }
+    }
+
+    fn parse_verbose_line(&mut self, line: &str) -> Result<(), BenchParseError> {
+        if gnu::is_verbose_start(line) {
+            self.verbose_result = Some(BenchmarkResult::new());
+        }
+        let bench = match self.verbose_result.as_mut() {
+            Some(bench) => bench,
+            None => {
+                return Err(
+                    self.line_error(ErrorReason::UnexpectedLine(InputFormat::GnuVerbose), line)
+                )
+            }
+        };
+        let done = gnu::parse_verbose_line(bench, line).map_err(|e| self.line_error(e, line))?;
+        if done {
+            let bench = self.verbose_result.take().expect("Missing verbose result");
+            self.dataset.result.push(bench);
+        }
+        Ok(())
+    }
+
+    // BSD results have no closing line, so they are complete
+    // once the next result or the end of the dataset is reached.
+    fn push_bsd_result(&mut self) -> Result<(), BenchParseError> {
+        if let Some(bench) = self.bsd_result.take() {
+            validate_result(&bench).map_err(|reason| BenchParseError {
+                path: None,
+                line: Some(self.bsd_line),
+                text: None,
+                reason,
+            })?;
+            self.dataset.result.push(bench);
+        }
+        Ok(())
+    }
+
+    fn capture_name(&self, line: &str) -> Result<String, BenchParseError> {
+        line.split(':')
+            .nth(1)
+            .map(|name| name.trim().to_string())
+            .ok_or_else(|| self.line_error(ErrorReason::MissingHeaderValue, line))
+    }
+
+    fn is_block_boundary(&self, line: &str) -> bool {
+        line.trim().is_empty() || line.starts_with("Dataset") || line.starts_with("Benchmarking")
+    }
+
+    fn line_error(&self, reason: ErrorReason, line: &str) -> BenchParseError {
+        BenchParseError::at_line(reason, self.line_num, line)
+    }
+
+    fn parse_records(&mut self) -> Records {
+        let mut recs = Records::new();
+        recs.machine = self.header.apply(&self.machine);
+        recs.segul_version = self.segul_version.clone();
+        recs.analysis = self.analysis.clone();
+        let mut bench = Benchmark::new();
+        bench.bench = self.bench_name.clone();
+        bench.line = self.bench_line;
+        bench.dataset.push(self.dataset.clone());
+        recs.benchmark = bench;
+        self.dataset.clear();
+
+        recs
+    }
+}
+
+impl<R: Read> Iterator for BenchReader<R> {
+    type Item = Result<Records, BenchParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reader_records() {
+        let input = "Model name: AMD Ryzen 9 3900X 12-Core Processor\n\
+            segul 0.18.1\n\
+            Analysis: concat-phylip\n\
+            Benchmarking SEGUL\n\
+            Dataset path: alignments/wu_2018_aa_loci/\n\
+            0:02.60 382304 126%\n\
+            0:02.61 382608 125%\n\
+            Dataset path: alignments/shen_2018_loci_aa/\n\
+            0:05.26 587408 113%\n\
+            0:05.05 602208 122%\n";
+        let records: Vec<Records> = BenchReader::new(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(2, records.len());
+        assert_eq!("0.18.1", records[0].segul_version);
+        assert_eq!("concat-phylip", records[0].analysis);
+        assert_eq!("Linux", records[0].machine.os);
+        let dataset = &records[1].benchmark.dataset[0];
+        assert_eq!("alignments/shen_2018_loci_aa/", dataset.name);
+        assert_eq!("0:05.05", dataset.result[1].exec_time);
+    }
+
+    #[test]
+    fn test_reader_machine_header() {
+        let input = "# host: ryzen\n\
+            # cpu: AMD Ryzen 9 3900X\n\
+            # cores: 12\n\
+            # ram: 64 GB\n\
+            # os: openSUSE Tumbleweed\n\
+            CPU(s):              24\n\
+            Model name:          AMD Ryzen 9 3900X 12-Core Processor\n\
+            5.16.11-1-default\n\
+            Benchmarking SEGUL\n\
+            Dataset path: alignments/wu_2018_aa_loci/\n\
+            0:02.60 382304 126%\n";
+        let records: Vec<Records> = BenchReader::new(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let machine = &records[0].machine;
+        assert_eq!("AMD Ryzen 9 3900X", machine.cpu);
+        assert_eq!("openSUSE Tumbleweed", machine.os);
+        assert_eq!("5.16.11-1-default", machine.kernel);
+        assert_eq!(Some(12), machine.physical_cores());
+        assert_eq!(Some(24), machine.logical_cpus());
+        assert_eq!(Some(67108864), machine.total_ram_kb);
+    }
+
+    #[test]
+    fn test_reader_intel_mac() {
+        let input = "Darwin Kernel Version 20.1.0: Sat Oct 31 00:07:11 PDT 2020; \
+            root:xnu-7195.50.7~2/RELEASE_X86_64\n\
+            Benchmarking SEGUL\n\
+            Dataset path: alignments/esselstyn_2021_nexus_trimmed\n\
+            0:15.68 549844 95%\n";
+        let records: Vec<Records> = BenchReader::new(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let machine = &records[0].machine;
+        assert_eq!("macOS (Mb Air)", machine.os);
+        assert_eq!("Intel Core i5-4260U", machine.cpu);
+        assert_eq!("20.1.0", machine.kernel);
+        assert_eq!(None, machine.threads);
+    }
+
+    #[test]
+    fn test_reader_malformed_line() {
+        let input = "Benchmarking SEGUL\n\
+            Dataset path: alignments/wu_2018_aa_loci/\n\
+            0:02.60 382304 126%\n\
+            0:02.61 382608\n";
+        let err = BenchReader::new(input.as_bytes())
+            .find_map(|r| r.err())
+            .unwrap();
+        assert_eq!(Some(4), err.line);
+        assert!(matches!(
+            err.reason,
+            ErrorReason::MissingColumns {
+                expected: 3,
+                found: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn test_reader_replicate_count() {
+        let input = "Benchmarking SEGUL\n\
+            Dataset path: alignments/wu_2018_aa_loci/\n\
+            0:02.60 382304 126%\n";
+        let err = BenchReader::new(input.as_bytes())
+            .expect_replicates(2)
+            .find_map(|r| r.err())
+            .unwrap();
+        assert_eq!(Some(2), err.line);
+        assert!(matches!(
+            err.reason,
+            ErrorReason::InvalidReplicateCount {
+                expected: 2,
+                found: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn test_reader_resumes_after_short_dataset() {
+        let input = "Benchmarking SEGUL\n\
+            Dataset path: alignments/wu_2018_aa_loci/\n\
+            0:02.60 382304 126%\n\
+            Dataset path: alignments/shen_2018_loci_aa/\n\
+            0:05.26 587408 113%\n\
+            0:05.05 602208 122%\n";
+        let records: Vec<_> = BenchReader::new(input.as_bytes())
+            .expect_replicates(2)
+            .collect();
+        assert_eq!(2, records.len());
+        assert!(records[0].is_err());
+        let recs = records[1].as_ref().unwrap();
+        assert_eq!(
+            "alignments/shen_2018_loci_aa/",
+            recs.benchmark.dataset[0].name
+        );
+    }
+
+    #[test]
+    fn test_reader_detects_replicates() {
+        let input = "Benchmarking SEGUL\n\
+            Dataset path: alignments/wu_2018_aa_loci/\n\
+            0:02.60 382304 126%\n\
+            0:02.61 382608 125%\n\
+            0:02.62 382609 125%\n\
+            \n\
+            Dataset path: alignments/shen_2018_loci_aa/\n\
+            0:05.26 587408 113%\n\
+            Benchmarking AMAS\n\
+            Dataset path: alignments/shen_2018_loci_aa/\n\
+            0:15.26 987408 99%\n\
+            0:15.05 902208 99%\n";
+        let records: Vec<Records> = BenchReader::new(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let counts: Vec<usize> = records
+            .iter()
+            .map(|r| r.benchmark.dataset[0].result.len())
+            .collect();
+        assert_eq!(vec![3, 1, 2], counts);
+        assert_eq!("Benchmarking SEGUL", records[1].benchmark.bench);
+        assert_eq!("Benchmarking AMAS", records[2].benchmark.bench);
+    }
+
+    #[test]
+    fn test_reader_verbose_results() {
+        let input = "Benchmarking SEGUL\n\
+            Dataset path: alignments/wu_2018_aa_loci/\n\
+            \tCommand being timed: \"segul alignment concat\"\n\
+            \tUser time (seconds): 2.71\n\
+            \tSystem time (seconds): 0.58\n\
+            \tPercent of CPU this job got: 126%\n\
+            \tElapsed (wall clock) time (h:mm:ss or m:ss): 0:02.60\n\
+            \tMaximum resident set size (kbytes): 382304\n\
+            \tExit status: 0\n\
+            \tCommand being timed: \"segul alignment concat\"\n\
+            \tPercent of CPU this job got: 125%\n\
+            \tElapsed (wall clock) time (h:mm:ss or m:ss): 0:02.61\n\
+            \tMaximum resident set size (kbytes): 382608\n\
+            \tExit status: 0\n";
+        let records: Vec<Records> = BenchReader::new(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let dataset = &records[0].benchmark.dataset[0];
+        assert_eq!(2, dataset.result.len());
+        assert_eq!("382608", dataset.result[1].mem_usage);
+        assert_eq!(Some(2.71), dataset.result[0].user_time);
+    }
+
+    #[test]
+    fn test_reader_bsd_results() {
+        let input = "Darwin Kernel Version 21.2.0: Sun Nov 28 20:29:10 PST 2021\n\
+            Benchmarking SEGUL\n\
+            Dataset path: alignments/wu_2018_aa_loci/\n\
+            \x20       2.60 real         3.00 user         0.28 sys\n\
+            \x20          391479296  maximum resident set size\n\
+            \x20              37080  page reclaims\n\
+            \x20       2.61 real         3.01 user         0.27 sys\n\
+            \x20          391790592  maximum resident set size\n\
+            \n\
+            Dataset path: alignments/shen_2018_loci_aa/\n\
+            \x20       5.26 real         5.00 user         0.94 sys\n\
+            \x20          601505792  maximum resident set size\n";
+        let records: Vec<Records> = BenchReader::new(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(2, records.len());
+        let dataset = &records[0].benchmark.dataset[0];
+        assert_eq!(2, dataset.result.len());
+        assert_eq!("382304", dataset.result[0].mem_usage);
+        assert_eq!("126%", dataset.result[0].cpu_usage);
+        assert_eq!(
+            "587408",
+            records[1].benchmark.dataset[0].result[0].mem_usage
+        );
+    }
+}
@@ -0,0 +1,358 @@
+//! Registries of the benchmark datasets, apps, and analyses.
+//!
+//! Maps the `Dataset path:` of each block to the publication
+//! and the dataset properties, the `Benchmarking` line to
+//! the app name and version, and the analysis to its display name.
+//! The built-in registries contain the datasets, apps, and analyses
+//! used in segul-bench.
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::error::{BenchParseError, ErrorReason};
+use crate::types::{AppType, Apps, Pubs};
+
+const BUILTIN_DATASETS: &str = include_str!("../assets/datasets.toml");
+const BUILTIN_APPS: &str = include_str!("../assets/apps.toml");
+const BUILTIN_ANALYSES: &str = include_str!("../assets/analyses.toml");
+
+#[derive(Debug, Deserialize)]
+struct RegistryFile {
+    #[serde(default)]
+    dataset: Vec<DatasetEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DatasetEntry {
+    pattern: String,
+    #[serde(default)]
+    regex: bool,
+    name: String,
+    ntax: usize,
+    #[serde(default)]
+    aln_counts: usize,
+    #[serde(default)]
+    site_counts: usize,
+    #[serde(default)]
+    char_counts: usize,
+    datatype: String,
+}
+
+#[derive(Debug)]
+enum Matcher {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn is_match(&self, dataset_path: &str) -> bool {
+        match self {
+            Matcher::Substring(pattern) => dataset_path.to_lowercase().contains(pattern),
+            Matcher::Regex(re) => re.is_match(dataset_path),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DatasetRegistry {
+    datasets: Vec<(Matcher, Pubs)>,
+}
+
+impl DatasetRegistry {
+    pub fn from_toml(toml: &str) -> Result<Self, ErrorReason> {
+        let file: RegistryFile =
+            toml::from_str(toml).map_err(|e| ErrorReason::InvalidConfig(e.to_string()))?;
+        let datasets = file
+            .dataset
+            .into_iter()
+            .map(|entry| {
+                let matcher = if entry.regex {
+                    Matcher::Regex(
+                        Regex::new(&entry.pattern)
+                            .map_err(|e| ErrorReason::InvalidConfig(e.to_string()))?,
+                    )
+                } else {
+                    Matcher::Substring(entry.pattern.to_lowercase())
+                };
+                let pubs = Pubs {
+                    name: entry.name,
+                    ntax: entry.ntax,
+                    aln_counts: entry.aln_counts,
+                    site_counts: entry.site_counts,
+                    char_counts: entry.char_counts,
+                    datatype: entry.datatype,
+                };
+                Ok((matcher, pubs))
+            })
+            .collect::<Result<_, ErrorReason>>()?;
+        Ok(Self { datasets })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, BenchParseError> {
+        let toml =
+            fs::read_to_string(path).map_err(|e| BenchParseError::from(e).with_path(path))?;
+        Self::from_toml(&toml).map_err(|e| BenchParseError::new(e).with_path(path))
+    }
+
+    /// Returns the first dataset that matches the path. Unknown datasets
+    /// are named after the path.
+    pub fn match_dataset(&self, dataset_path: &str) -> Pubs {
+        match self.datasets.iter().find(|(m, _)| m.is_match(dataset_path)) {
+            Some((_, pubs)) => pubs.clone(),
+            None => {
+                let mut pubs = Pubs::new();
+                pubs.name = dataset_path.to_lowercase();
+                pubs.datatype = String::from("Whole Genome");
+                pubs
+            }
+        }
+    }
+}
+
+impl Default for DatasetRegistry {
+    fn default() -> Self {
+        Self::from_toml(BUILTIN_DATASETS).expect("Invalid built-in dataset registry")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AppFile {
+    #[serde(default)]
+    app: Vec<AppEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AppEntry {
+    pattern: String,
+    name: String,
+    #[serde(default)]
+    version: Option<VersionRule>,
+    #[serde(rename = "type")]
+    app_type: AppType,
+    #[serde(default)]
+    os: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum VersionRule {
+    Fixed(String),
+    From { from: VersionSource },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum VersionSource {
+    Segul,
+}
+
+#[derive(Debug)]
+struct AppRule {
+    pattern: Regex,
+    name: String,
+    version: Option<VersionRule>,
+    app_type: AppType,
+    os: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct AppRegistry {
+    rules: Vec<AppRule>,
+}
+
+impl AppRegistry {
+    pub fn from_toml(toml: &str) -> Result<Self, ErrorReason> {
+        let file: AppFile =
+            toml::from_str(toml).map_err(|e| ErrorReason::InvalidConfig(e.to_string()))?;
+        let rules = file
+            .app
+            .into_iter()
+            .map(|entry| {
+                Ok(AppRule {
+                    pattern: Regex::new(&entry.pattern)
+                        .map_err(|e| ErrorReason::InvalidConfig(e.to_string()))?,
+                    name: entry.name,
+                    version: entry.version,
+                    app_type: entry.app_type,
+                    os: entry.os,
+                })
+            })
+            .collect::<Result<_, ErrorReason>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, BenchParseError> {
+        let toml =
+            fs::read_to_string(path).map_err(|e| BenchParseError::from(e).with_path(path))?;
+        Self::from_toml(&toml).map_err(|e| BenchParseError::new(e).with_path(path))
+    }
+
+    /// Returns the app of the first matching rule. `segul_version` is
+    /// the version from the `segul` line in the log, if any.
+    /// Unknown apps are named after the benchmark line.
+    pub fn match_app(&self, bench: &str, segul_version: &str) -> Apps {
+        let mut apps = Apps::new();
+        let rule = self
+            .rules
+            .iter()
+            .find_map(|r| r.pattern.captures(bench).map(|caps| (r, caps)));
+        match rule {
+            Some((rule, caps)) => {
+                caps.expand(&rule.name, &mut apps.name);
+                apps.version = match &rule.version {
+                    Some(VersionRule::Fixed(version)) => version.clone(),
+                    Some(VersionRule::From {
+                        from: VersionSource::Segul,
+                    }) if !segul_version.is_empty() => format!("v{}", segul_version),
+                    _ => String::from("Unknown"),
+                };
+                apps.app_type = rule.app_type;
+                apps.os = rule.os.as_ref().map(|os| {
+                    let mut expanded = String::new();
+                    caps.expand(os, &mut expanded);
+                    expanded
+                });
+            }
+            None => {
+                apps.name = String::from(bench);
+                apps.version = String::from("Unknown");
+            }
+        }
+        apps
+    }
+}
+
+impl Default for AppRegistry {
+    fn default() -> Self {
+        Self::from_toml(BUILTIN_APPS).expect("Invalid built-in app registry")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnalysisRegistry {
+    default_format: String,
+    #[serde(default)]
+    analysis: HashMap<String, String>,
+    #[serde(default)]
+    format: HashMap<String, String>,
+}
+
+impl AnalysisRegistry {
+    pub fn from_toml(toml: &str) -> Result<Self, ErrorReason> {
+        toml::from_str(toml).map_err(|e| ErrorReason::InvalidConfig(e.to_string()))
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, BenchParseError> {
+        let toml =
+            fs::read_to_string(path).map_err(|e| BenchParseError::from(e).with_path(path))?;
+        Self::from_toml(&toml).map_err(|e| BenchParseError::new(e).with_path(path))
+    }
+
+    /// Returns the display name of an analysis given as `<analysis>` or
+    /// `<analysis>-<format>`, e.g. `Alignment Concatenation (PHYLIP)`
+    /// for `concat-phylip`. Without a format, the default format is used.
+    pub fn match_analysis(&self, analysis: &str) -> String {
+        let (name, format) = match analysis.split_once('-') {
+            Some((name, format)) => (name, Some(format)),
+            None => (analysis, None),
+        };
+        let name = self.analysis.get(name).map_or(name, |n| n.as_str());
+        let format = match format {
+            Some(format) => self
+                .format
+                .get(&format.to_lowercase())
+                .cloned()
+                .unwrap_or_else(|| format.to_uppercase()),
+            None => self.default_format.clone(),
+        };
+        format!("{} ({})", name, format)
+    }
+}
+
+impl Default for AnalysisRegistry {
+    fn default() -> Self {
+        Self::from_toml(BUILTIN_ANALYSES).expect("Invalid built-in analysis registry")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_registry() {
+        let registry = DatasetRegistry::default();
+        let pubs = registry.match_dataset("alignments/oliveros_2019_80p_trimmed");
+        assert_eq!("Oliveros et al. 2019", pubs.name);
+        assert_eq!(221, pubs.ntax);
+        assert_eq!(522529858, pubs.char_counts);
+        let genome = registry.match_dataset("reads/SRR26062012");
+        assert_eq!(243874896842, genome.char_counts);
+    }
+
+    #[test]
+    fn test_regex_registry() {
+        let toml = r#"
+            [[dataset]]
+            pattern = '^alignments/new_\d{4}'
+            regex = true
+            name = "New et al. 2024"
+            ntax = 12
+            datatype = "DNA"
+        "#;
+        let registry = DatasetRegistry::from_toml(toml).unwrap();
+        assert_eq!(
+            "New et al. 2024",
+            registry.match_dataset("alignments/new_2024_loci").name
+        );
+        let unknown = registry.match_dataset("alignments/Other");
+        assert_eq!("alignments/other", unknown.name);
+        assert_eq!(0, unknown.ntax);
+    }
+
+    #[test]
+    fn test_builtin_apps() {
+        let registry = AppRegistry::default();
+        let apps = registry.match_app("Benchmarking SEGUL ignore datatype", "0.18.1");
+        assert_eq!("SEGUL CLI (--datatype ignore)", apps.name);
+        assert_eq!("v0.18.1", apps.version);
+        let apps = registry.match_app("Benchmarking SEGUL GUI macOS", "0.18.1");
+        assert_eq!("SEGUL GUI (macOS)", apps.name);
+        assert_eq!(AppType::Gui, apps.app_type);
+        assert_eq!(Some(String::from("macOS")), apps.os);
+        let apps = registry.match_app("python3 AMAS.py concat -i alignments/*", "");
+        assert_eq!("AMAS", apps.name);
+        assert_eq!("v1.02", apps.version);
+        let apps = registry.match_app("Benchmarking goalign", "0.18.1");
+        assert_eq!("Unknown", apps.version);
+    }
+
+    #[test]
+    fn test_analysis_registry() {
+        let registry = AnalysisRegistry::default();
+        assert_eq!(
+            "Alignment Concatenation (NEXUS)",
+            registry.match_analysis("concat")
+        );
+        assert_eq!(
+            "Alignment Conversion (PHYLIP)",
+            registry.match_analysis("convert-phylip")
+        );
+        assert_eq!("filter (NEXUS)", registry.match_analysis("filter"));
+        let toml = r#"
+            default_format = "FASTA"
+
+            [analysis]
+            filter = "Alignment Filtering"
+        "#;
+        let registry = AnalysisRegistry::from_toml(toml).unwrap();
+        assert_eq!(
+            "Alignment Filtering (FASTA)",
+            registry.match_analysis("filter")
+        );
+        assert_eq!("concat (NEX)", registry.match_analysis("concat-nex"));
+    }
+}
@@ -0,0 +1,339 @@
+//! Output rows, one per replicate.
+use lazy_static::lazy_static;
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+
+lazy_static! {
+    /// Output columns, from the field names of [`OutputRow`].
+    pub(crate) static ref HEADER: Vec<String> = OutputRow::from(&BenchRow::new())
+        .to_map()
+        .expect("Failed to serialize the header")
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect();
+}
+
+/// A replicate with the app, dataset, and machine it ran on.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BenchRow {
+    #[serde(rename = "Apps")]
+    pub app: String,
+    #[serde(rename = "Version")]
+    pub version: String,
+    #[serde(rename = "Pubs")]
+    pub pubs: String,
+    #[serde(rename = "Datasets")]
+    pub dataset: String,
+    #[serde(rename = "NTAX")]
+    pub ntax: usize,
+    #[serde(rename = "Character_counts")]
+    pub char_counts: usize,
+    #[serde(rename = "Alignment_counts")]
+    pub aln_counts: usize,
+    #[serde(rename = "Site_counts")]
+    pub site_counts: usize,
+    #[serde(rename = "Datatype")]
+    pub datatype: String,
+    #[serde(rename = "Analyses")]
+    pub analysis: String,
+    #[serde(rename = "Platform")]
+    pub platform: String,
+    #[serde(rename = "App_type")]
+    pub app_type: String,
+    #[serde(rename = "OS_name")]
+    pub os: String,
+    #[serde(rename = "CPU")]
+    pub cpu: String,
+    #[serde(rename = "Benchmark_dates")]
+    pub date: String,
+    #[serde(rename = "Latest_bench")]
+    pub latest_bench: bool,
+    /// Elapsed time as `[h:]mm:ss.ss`.
+    #[serde(rename = "Execution_time")]
+    pub exec_time: String,
+    #[serde(rename = "RAM_usage_kb")]
+    pub ram_usage_kb: Option<u64>,
+    #[serde(rename = "Percent_CPU_usage")]
+    pub cpu_usage: Option<f64>,
+    #[serde(rename = "Execution_time_secs")]
+    pub exec_time_secs: f64,
+    #[serde(rename = "RAM_usage_Mb", serialize_with = "serialize_f32")]
+    pub ram_usage_mb: Option<f32>,
+    #[serde(rename = "Replicates")]
+    pub replicates: usize,
+    /// Position of the replicate in its dataset block of the log,
+    /// from 1, counting the warm-up replicates.
+    #[serde(skip)]
+    pub replicate: usize,
+    /// `Benchmarking` header of the block and its line in the log,
+    /// which tell apart two blocks of an app on the same dataset.
+    #[serde(skip)]
+    pub bench: String,
+    #[serde(skip)]
+    pub bench_line: usize,
+    #[serde(rename = "User_time_secs")]
+    pub user_time_secs: Option<f64>,
+    #[serde(rename = "System_time_secs")]
+    pub system_time_secs: Option<f64>,
+    #[serde(rename = "Major_page_faults")]
+    pub major_page_faults: Option<u64>,
+    #[serde(rename = "Minor_page_faults")]
+    pub minor_page_faults: Option<u64>,
+    #[serde(rename = "Voluntary_context_switches")]
+    pub voluntary_switches: Option<u64>,
+    #[serde(rename = "Involuntary_context_switches")]
+    pub involuntary_switches: Option<u64>,
+    #[serde(rename = "File_system_inputs")]
+    pub fs_inputs: Option<u64>,
+    #[serde(rename = "File_system_outputs")]
+    pub fs_outputs: Option<u64>,
+    #[serde(rename = "Exit_status")]
+    pub exit_status: Option<i32>,
+    #[serde(rename = "Instructions_retired")]
+    pub instructions_retired: Option<u64>,
+    #[serde(rename = "Peak_memory_footprint_kb")]
+    pub peak_memory_kb: Option<u64>,
+    #[serde(rename = "Core_counts")]
+    pub cores: Option<usize>,
+    #[serde(rename = "Thread_counts")]
+    pub threads: Option<usize>,
+    #[serde(rename = "Total_RAM_kb")]
+    pub total_ram_kb: Option<u64>,
+    #[serde(rename = "Kernel_version")]
+    pub kernel: String,
+    #[serde(rename = "Percent_CPU_usage_normalized")]
+    pub cpu_usage_normalized: Option<f64>,
+    /// Warm-up replicates are left out of the statistics.
+    #[serde(rename = "Warmup")]
+    pub warmup: bool,
+    #[serde(rename = "Outlier")]
+    pub outlier: bool,
+}
+
+impl BenchRow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Machine the replicate ran on, used to group the rows.
+    pub fn machine(&self) -> String {
+        format!("{} ({})", self.os, self.cpu)
+    }
+
+    /// Throughput in megabases of characters per second.
+    pub fn mbases_per_sec(&self) -> Option<f64> {
+        per(self.mbases()?, self.exec_time_secs)
+    }
+
+    pub fn alignments_per_sec(&self) -> Option<f64> {
+        per(self.aln_counts as f64, self.exec_time_secs)
+    }
+
+    /// Peak memory usage in MB per megabase of characters.
+    pub fn ram_usage_mb_per_mbase(&self) -> Option<f64> {
+        per(f64::from(self.ram_usage_mb?), self.mbases()?)
+    }
+
+    pub fn secs_per_1k_alignments(&self) -> Option<f64> {
+        per(self.exec_time_secs, self.aln_counts as f64 / 1000.0)
+    }
+
+    fn mbases(&self) -> Option<f64> {
+        per(self.char_counts as f64, 1_000_000.0)
+    }
+}
+
+/// A row as written into the outputs: the replicate
+/// and its derived metrics, under the CSV column names.
+#[derive(Debug, Serialize)]
+pub(crate) struct OutputRow<'a> {
+    #[serde(flatten)]
+    row: &'a BenchRow,
+    #[serde(flatten)]
+    metrics: DerivedMetrics,
+}
+
+#[derive(Debug, Serialize)]
+struct DerivedMetrics {
+    #[serde(rename = "Mbases_per_sec")]
+    mbases_per_sec: Option<f64>,
+    #[serde(rename = "Alignments_per_sec")]
+    alignments_per_sec: Option<f64>,
+    #[serde(rename = "RAM_usage_Mb_per_Mbase")]
+    ram_usage_mb_per_mbase: Option<f64>,
+    #[serde(rename = "Secs_per_1k_alignments")]
+    secs_per_1k_alignments: Option<f64>,
+}
+
+impl<'a> From<&'a BenchRow> for OutputRow<'a> {
+    fn from(row: &'a BenchRow) -> Self {
+        Self {
+            row,
+            metrics: DerivedMetrics {
+                mbases_per_sec: row.mbases_per_sec(),
+                alignments_per_sec: row.alignments_per_sec(),
+                ram_usage_mb_per_mbase: row.ram_usage_mb_per_mbase(),
+                secs_per_1k_alignments: row.secs_per_1k_alignments(),
+            },
+        }
+    }
+}
+
+impl OutputRow<'_> {
+    /// Typed fields in the order of [`HEADER`].
+    pub(crate) fn to_map(&self) -> serde_json::Result<Map<String, Value>> {
+        match serde_json::to_value(self)? {
+            Value::Object(map) => Ok(map),
+            _ => unreachable!("Rows serialize into objects"),
+        }
+    }
+
+    /// Fields as written into CSV. Flags are `TRUE` or `FALSE`, numbers
+    /// are formatted with `Display`, e.g. `142` rather than `142.0`,
+    /// and missing values are empty.
+    pub(crate) fn to_record(&self) -> serde_json::Result<Vec<String>> {
+        Ok(self.to_map()?.values().map(format_field).collect())
+    }
+}
+
+fn format_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(value) => format_bool(*value).to_string(),
+        Value::String(value) => value.clone(),
+        Value::Number(number) => match number.as_f64() {
+            Some(value) if number.is_f64() => value.to_string(),
+            _ => number.to_string(),
+        },
+        value => value.to_string(),
+    }
+}
+
+// Ratio of positive values. Datasets without counts and
+// replicates without time have no derived metrics.
+fn per(value: f64, unit: f64) -> Option<f64> {
+    if value > 0.0 && unit > 0.0 {
+        Some(value / unit)
+    } else {
+        None
+    }
+}
+
+// Widens through the shortest decimal, so 344.8 stays
+// 344.8 instead of 344.79998779296875.
+fn serialize_f32<S: Serializer>(value: &Option<f32>, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => {
+            let value: f64 = value.to_string().parse().expect("Failed to widen f32");
+            serializer.serialize_f64(value)
+        }
+        None => serializer.serialize_none(),
+    }
+}
+
+fn format_bool(value: bool) -> &'static str {
+    if value {
+        "TRUE"
+    } else {
+        "FALSE"
+    }
+}
+
+pub(crate) fn format_optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Builds the rows of the unit tests.
+#[cfg(test)]
+pub(crate) struct RowBuilder {
+    row: BenchRow,
+}
+
+#[cfg(test)]
+impl RowBuilder {
+    pub(crate) fn new(app: &str, dataset: &str, secs: f64) -> Self {
+        let mut row = BenchRow::new();
+        row.app = String::from(app);
+        row.dataset = String::from(dataset);
+        row.exec_time_secs = secs;
+        Self { row }
+    }
+
+    pub(crate) fn version(mut self, version: &str) -> Self {
+        self.row.version = String::from(version);
+        self
+    }
+
+    pub(crate) fn os(mut self, os: &str) -> Self {
+        self.row.os = String::from(os);
+        self
+    }
+
+    pub(crate) fn date(mut self, date: &str) -> Self {
+        self.row.date = String::from(date);
+        self
+    }
+
+    pub(crate) fn size(mut self, char_counts: usize, ntax: usize) -> Self {
+        self.row.char_counts = char_counts;
+        self.row.ntax = ntax;
+        self
+    }
+
+    pub(crate) fn ram_mb(mut self, mb: f32) -> Self {
+        self.row.ram_usage_mb = Some(mb);
+        self
+    }
+
+    pub(crate) fn ram_kb(mut self, kb: u64) -> Self {
+        self.row.ram_usage_kb = Some(kb);
+        self
+    }
+
+    pub(crate) fn replicate(mut self, replicate: usize) -> Self {
+        self.row.replicate = replicate;
+        self
+    }
+
+    pub(crate) fn bench(mut self, bench: &str, line: usize) -> Self {
+        self.row.bench = String::from(bench);
+        self.row.bench_line = line;
+        self
+    }
+
+    pub(crate) fn warmup(mut self) -> Self {
+        self.row.warmup = true;
+        self
+    }
+
+    pub(crate) fn outlier(mut self) -> Self {
+        self.row.outlier = true;
+        self
+    }
+
+    pub(crate) fn build(self) -> BenchRow {
+        self.row
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derived_metrics() {
+        let mut row = BenchRow::new();
+        row.char_counts = 257_100_000;
+        row.aln_counts = 5162;
+        row.exec_time_secs = 2.5;
+        row.ram_usage_mb = Some(344.8);
+        assert_eq!(Some(102.84), row.mbases_per_sec());
+        assert_eq!(Some(2064.8), row.alignments_per_sec());
+        assert!((row.ram_usage_mb_per_mbase().unwrap() - 1.341112).abs() < 1e-6);
+        assert!((row.secs_per_1k_alignments().unwrap() - 0.484308).abs() < 1e-6);
+        row.exec_time_secs = 0.0;
+        row.ram_usage_mb = None;
+        assert_eq!(None, row.mbases_per_sec());
+        assert_eq!(None, row.ram_usage_mb_per_mbase());
+    }
+}
@@ -0,0 +1,196 @@
+//! Empirical scaling of each app with the dataset size.
+//!
+//! For each app, version, analysis, and machine, the median time and
+//! memory usage of every dataset are fitted against the character
+//! count and the number of taxa on a log-log scale. The slope is the
+//! exponent `b` in `y = a * x^b`. Datasets without counts are skipped.
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::row::{format_optional, BenchRow};
+use crate::stats::{self, LinearFit};
+use crate::summary::group_rows;
+
+#[derive(Debug, Clone)]
+pub struct Scaling {
+    pub app: String,
+    pub version: String,
+    pub analysis: String,
+    pub os: String,
+    pub cpu: String,
+    /// Number of datasets with counts. Each fit has its own number
+    /// of points, without the datasets missing the fitted value.
+    pub datasets: usize,
+    pub time_vs_chars: Option<LinearFit>,
+    pub memory_vs_chars: Option<LinearFit>,
+    pub time_vs_ntax: Option<LinearFit>,
+    pub memory_vs_ntax: Option<LinearFit>,
+}
+
+// Medians of a dataset group.
+struct Point {
+    char_counts: f64,
+    ntax: f64,
+    exec_time: Option<f64>,
+    ram_usage: Option<f64>,
+}
+
+pub fn fit_scaling(rows: &[BenchRow], exclude_outliers: bool) -> Vec<Scaling> {
+    let mut index: HashMap<(&str, &str, &str, String), usize> = HashMap::new();
+    let mut blocks: Vec<(&BenchRow, Vec<Point>)> = Vec::new();
+    for group in group_rows(rows, exclude_outliers) {
+        let first = group[0];
+        if first.char_counts == 0 || first.ntax == 0 {
+            continue;
+        }
+        let exec_times: Vec<f64> = group.iter().map(|r| r.exec_time_secs).collect();
+        let mem_usages: Vec<f64> = group
+            .iter()
+            .filter_map(|r| r.ram_usage_mb.map(f64::from))
+            .collect();
+        let point = Point {
+            char_counts: first.char_counts as f64,
+            ntax: first.ntax as f64,
+            exec_time: stats::median(&exec_times),
+            ram_usage: stats::median(&mem_usages),
+        };
+        let key = (
+            first.app.as_str(),
+            first.version.as_str(),
+            first.analysis.as_str(),
+            first.machine(),
+        );
+        match index.get(&key) {
+            Some(&i) => blocks[i].1.push(point),
+            None => {
+                index.insert(key, blocks.len());
+                blocks.push((first, vec![point]));
+            }
+        }
+    }
+    blocks
+        .iter()
+        .map(|(first, points)| Scaling {
+            app: first.app.clone(),
+            version: first.version.clone(),
+            analysis: first.analysis.clone(),
+            os: first.os.clone(),
+            cpu: first.cpu.clone(),
+            datasets: points.len(),
+            time_vs_chars: log_log_fit(points, |p| p.char_counts, |p| p.exec_time),
+            memory_vs_chars: log_log_fit(points, |p| p.char_counts, |p| p.ram_usage),
+            time_vs_ntax: log_log_fit(points, |p| p.ntax, |p| p.exec_time),
+            memory_vs_ntax: log_log_fit(points, |p| p.ntax, |p| p.ram_usage),
+        })
+        .collect()
+}
+
+fn log_log_fit<X, Y>(points: &[Point], x: X, y: Y) -> Option<LinearFit>
+where
+    X: Fn(&Point) -> f64,
+    Y: Fn(&Point) -> Option<f64>,
+{
+    let (xs, ys): (Vec<f64>, Vec<f64>) = points
+        .iter()
+        .filter_map(|p| y(p).filter(|v| *v > 0.0).map(|v| (x(p).ln(), v.ln())))
+        .unzip();
+    stats::linear_regression(&xs, &ys)
+}
+
+pub(crate) fn write_scaling<W: Write>(writer: &mut W, scaling: &[Scaling]) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    let mut header: Vec<String> = ["Apps", "Version", "Analyses", "OS_name", "CPU", "Datasets"]
+        .iter()
+        .map(|h| h.to_string())
+        .collect();
+    for fit in [
+        "Time_vs_chars",
+        "Memory_vs_chars",
+        "Time_vs_ntax",
+        "Memory_vs_ntax",
+    ] {
+        header.push(format!("{}_exponent", fit));
+        header.push(format!("{}_r_squared", fit));
+        header.push(format!("{}_n", fit));
+    }
+    writer.write_record(&header)?;
+    for s in scaling {
+        let mut record = vec![
+            s.app.clone(),
+            s.version.clone(),
+            s.analysis.clone(),
+            s.os.clone(),
+            s.cpu.clone(),
+            s.datasets.to_string(),
+        ];
+        for fit in [
+            s.time_vs_chars,
+            s.memory_vs_chars,
+            s.time_vs_ntax,
+            s.memory_vs_ntax,
+        ] {
+            record.push(format_optional(fit.map(|f| f.slope)));
+            record.push(format_optional(fit.and_then(|f| f.r_squared)));
+            record.push(format_optional(fit.map(|f| f.n)));
+        }
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::row::RowBuilder;
+
+    #[test]
+    fn test_fit_scaling() {
+        // Time grows with the square of the characters.
+        let rows = [
+            ("Wu", 1_000_000, 10, 1.0),
+            ("Wu", 1_000_000, 10, 1.0),
+            ("Shen", 2_000_000, 40, 4.0),
+            ("Chan", 4_000_000, 20, 16.0),
+            ("Unknown", 0, 0, 3.0),
+        ]
+        .map(|(dataset, char_counts, ntax, secs)| {
+            RowBuilder::new("SEGUL CLI", dataset, secs)
+                .size(char_counts, ntax)
+                .ram_mb(100.0)
+                .build()
+        });
+        let scaling = fit_scaling(&rows, false);
+        assert_eq!(1, scaling.len());
+        assert_eq!(3, scaling[0].datasets);
+        let fit = scaling[0].time_vs_chars.unwrap();
+        assert!((fit.slope - 2.0).abs() < 1e-9);
+        assert!((fit.r_squared.unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(3, fit.n);
+        assert_eq!(0.0, scaling[0].memory_vs_chars.unwrap().slope);
+        assert!(scaling[0].time_vs_ntax.unwrap().r_squared.unwrap() < 1.0);
+    }
+
+    #[test]
+    fn test_fit_scaling_points() {
+        let rows = [
+            RowBuilder::new("SEGUL CLI", "Wu", 1.0)
+                .size(1_000_000, 10)
+                .ram_mb(100.0)
+                .build(),
+            RowBuilder::new("SEGUL CLI", "Shen", 4.0)
+                .size(2_000_000, 40)
+                .ram_mb(100.0)
+                .build(),
+            RowBuilder::new("SEGUL CLI", "Chan", 16.0)
+                .size(4_000_000, 20)
+                .build(),
+        ];
+        let scaling = fit_scaling(&rows, false);
+        assert_eq!(3, scaling[0].datasets);
+        assert_eq!(3, scaling[0].time_vs_chars.unwrap().n);
+        let memory = scaling[0].memory_vs_chars.unwrap();
+        assert_eq!(2, memory.n);
+        assert_eq!(None, memory.r_squared);
+    }
+}
@@ -0,0 +1,371 @@
+//! SQLite output with a normalized schema.
+//!
+//! Machines, apps, and datasets are shared by the runs. A run is a
+//! `Benchmarking` block of an app on a dataset, analysis, machine, and
+//! date, told apart by its header and line in the log. It has one row
+//! per replicate, numbered by its position in the block. Machines are
+//! keyed by their hardware and kernel only, so the OS name and the
+//! platform, which an app may override, are kept on the runs. Every table has a
+//! natural unique key, so writing the same rows again updates them
+//! instead of adding duplicates.
+use std::collections::HashSet;
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+use crate::error::BenchParseError;
+use crate::row::BenchRow;
+
+const SCHEMA: &str = "
+PRAGMA foreign_keys = ON;
+
+CREATE TABLE IF NOT EXISTS machines (
+    id INTEGER PRIMARY KEY,
+    cpu TEXT NOT NULL,
+    kernel_version TEXT NOT NULL,
+    core_counts INTEGER,
+    thread_counts INTEGER,
+    total_ram_kb INTEGER,
+    UNIQUE (cpu, kernel_version, core_counts, thread_counts, total_ram_kb)
+);
+
+CREATE TABLE IF NOT EXISTS apps (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    version TEXT NOT NULL,
+    app_type TEXT NOT NULL,
+    UNIQUE (name, version)
+);
+
+CREATE TABLE IF NOT EXISTS datasets (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    pubs TEXT NOT NULL,
+    ntax INTEGER NOT NULL,
+    character_counts INTEGER NOT NULL,
+    alignment_counts INTEGER NOT NULL,
+    site_counts INTEGER NOT NULL,
+    datatype TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    machine_id INTEGER NOT NULL REFERENCES machines (id),
+    app_id INTEGER NOT NULL REFERENCES apps (id),
+    dataset_id INTEGER NOT NULL REFERENCES datasets (id),
+    analysis TEXT NOT NULL,
+    benchmark_date TEXT NOT NULL,
+    os_name TEXT NOT NULL,
+    platform TEXT NOT NULL,
+    bench TEXT NOT NULL,
+    bench_line INTEGER NOT NULL,
+    latest_bench INTEGER NOT NULL,
+    replicates INTEGER NOT NULL,
+    UNIQUE (machine_id, app_id, dataset_id, analysis, benchmark_date, bench, bench_line)
+);
+
+CREATE TABLE IF NOT EXISTS replicates (
+    id INTEGER PRIMARY KEY,
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    replicate INTEGER NOT NULL,
+    execution_time TEXT NOT NULL,
+    execution_time_secs REAL NOT NULL,
+    ram_usage_kb INTEGER,
+    percent_cpu_usage REAL,
+    percent_cpu_usage_normalized REAL,
+    user_time_secs REAL,
+    system_time_secs REAL,
+    major_page_faults INTEGER,
+    minor_page_faults INTEGER,
+    voluntary_context_switches INTEGER,
+    involuntary_context_switches INTEGER,
+    file_system_inputs INTEGER,
+    file_system_outputs INTEGER,
+    exit_status INTEGER,
+    instructions_retired INTEGER,
+    peak_memory_footprint_kb INTEGER,
+    warmup INTEGER NOT NULL,
+    outlier INTEGER NOT NULL,
+    UNIQUE (run_id, replicate)
+);
+";
+
+/// Writes the rows into the database at `path`, creating the tables
+/// if needed. The rows are written in a single transaction.
+pub(crate) fn write_rows<'a, I>(path: &Path, rows: I) -> Result<(), BenchParseError>
+where
+    I: IntoIterator<Item = &'a BenchRow>,
+{
+    let mut conn = Connection::open(path)?;
+    insert_rows(&mut conn, rows)
+}
+
+fn insert_rows<'a, I>(conn: &mut Connection, rows: I) -> Result<(), BenchParseError>
+where
+    I: IntoIterator<Item = &'a BenchRow>,
+{
+    conn.execute_batch(SCHEMA)?;
+    let tx = conn.transaction()?;
+    // Replicates of a run that the rows no longer have, e.g. dropped
+    // warm-up replicates, are removed before the first one is written.
+    let mut runs: HashSet<i64> = HashSet::new();
+    for row in rows {
+        let run_id = insert_run(&tx, row)?;
+        if runs.insert(run_id) {
+            tx.execute("DELETE FROM replicates WHERE run_id = ?1", [run_id])?;
+        }
+        insert_replicate(&tx, run_id, row)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn insert_run(tx: &Transaction, row: &BenchRow) -> rusqlite::Result<i64> {
+    let machine = params![
+        row.cpu,
+        row.kernel,
+        row.cores,
+        row.threads,
+        row.total_ram_kb
+    ];
+    // Unknown counts are NULL, which never conflict, so the machine
+    // is looked up before it is inserted.
+    let machine_id: i64 = match tx
+        .query_row(
+            "SELECT id FROM machines
+            WHERE cpu = ?1 AND kernel_version = ?2 AND core_counts IS ?3
+                AND thread_counts IS ?4 AND total_ram_kb IS ?5",
+            machine,
+            |r| r.get(0),
+        )
+        .optional()?
+    {
+        Some(id) => id,
+        None => {
+            tx.execute(
+                "INSERT INTO machines
+                    (cpu, kernel_version, core_counts, thread_counts, total_ram_kb)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                machine,
+            )?;
+            tx.last_insert_rowid()
+        }
+    };
+    let app_id: i64 = tx.query_row(
+        "INSERT INTO apps (name, version, app_type) VALUES (?1, ?2, ?3)
+        ON CONFLICT (name, version) DO UPDATE SET app_type = excluded.app_type
+        RETURNING id",
+        params![row.app, row.version, row.app_type],
+        |r| r.get(0),
+    )?;
+    let dataset_id: i64 = tx.query_row(
+        "INSERT INTO datasets
+            (name, pubs, ntax, character_counts, alignment_counts, site_counts, datatype)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        ON CONFLICT (name) DO UPDATE SET
+            pubs = excluded.pubs,
+            ntax = excluded.ntax,
+            character_counts = excluded.character_counts,
+            alignment_counts = excluded.alignment_counts,
+            site_counts = excluded.site_counts,
+            datatype = excluded.datatype
+        RETURNING id",
+        params![
+            row.dataset,
+            row.pubs,
+            row.ntax,
+            row.char_counts,
+            row.aln_counts,
+            row.site_counts,
+            row.datatype
+        ],
+        |r| r.get(0),
+    )?;
+    tx.query_row(
+        "INSERT INTO runs
+            (machine_id, app_id, dataset_id, analysis, benchmark_date, os_name, platform,
+            bench, bench_line, latest_bench, replicates)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+        ON CONFLICT (machine_id, app_id, dataset_id, analysis, benchmark_date, bench, bench_line)
+        DO UPDATE SET
+            os_name = excluded.os_name,
+            platform = excluded.platform,
+            latest_bench = excluded.latest_bench,
+            replicates = excluded.replicates
+        RETURNING id",
+        params![
+            machine_id,
+            app_id,
+            dataset_id,
+            row.analysis,
+            row.date,
+            row.os,
+            row.platform,
+            row.bench,
+            row.bench_line,
+            row.latest_bench,
+            row.replicates
+        ],
+        |r| r.get(0),
+    )
+}
+
+fn insert_replicate(tx: &Transaction, run_id: i64, row: &BenchRow) -> rusqlite::Result<()> {
+    tx.execute(
+        "INSERT INTO replicates (
+            run_id, replicate, execution_time, execution_time_secs, ram_usage_kb,
+            percent_cpu_usage, percent_cpu_usage_normalized, user_time_secs, system_time_secs,
+            major_page_faults, minor_page_faults,
+            voluntary_context_switches, involuntary_context_switches,
+            file_system_inputs, file_system_outputs, exit_status,
+            instructions_retired, peak_memory_footprint_kb, warmup, outlier
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10,
+            ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)
+        ON CONFLICT (run_id, replicate) DO UPDATE SET
+            execution_time = excluded.execution_time,
+            execution_time_secs = excluded.execution_time_secs,
+            ram_usage_kb = excluded.ram_usage_kb,
+            percent_cpu_usage = excluded.percent_cpu_usage,
+            percent_cpu_usage_normalized = excluded.percent_cpu_usage_normalized,
+            user_time_secs = excluded.user_time_secs,
+            system_time_secs = excluded.system_time_secs,
+            major_page_faults = excluded.major_page_faults,
+            minor_page_faults = excluded.minor_page_faults,
+            voluntary_context_switches = excluded.voluntary_context_switches,
+            involuntary_context_switches = excluded.involuntary_context_switches,
+            file_system_inputs = excluded.file_system_inputs,
+            file_system_outputs = excluded.file_system_outputs,
+            exit_status = excluded.exit_status,
+            instructions_retired = excluded.instructions_retired,
+            peak_memory_footprint_kb = excluded.peak_memory_footprint_kb,
+            warmup = excluded.warmup,
+            outlier = excluded.outlier",
+        params![
+            run_id,
+            row.replicate,
+            row.exec_time,
+            row.exec_time_secs,
+            row.ram_usage_kb,
+            row.cpu_usage,
+            row.cpu_usage_normalized,
+            row.user_time_secs,
+            row.system_time_secs,
+            row.major_page_faults,
+            row.minor_page_faults,
+            row.voluntary_switches,
+            row.involuntary_switches,
+            row.fs_inputs,
+            row.fs_outputs,
+            row.exit_status,
+            row.instructions_retired,
+            row.peak_memory_kb,
+            row.warmup,
+            row.outlier
+        ],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::row::RowBuilder;
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |r| r.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_write_rows_idempotent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let mut rows =
+            [("Wu", 2.6, 1), ("Wu", 2.7, 2), ("Shen", 5.1, 1)].map(|(dataset, secs, i)| {
+                RowBuilder::new("SEGUL CLI", dataset, secs)
+                    .version("v0.18.1")
+                    .os("Linux")
+                    .replicate(i)
+                    .build()
+            });
+        insert_rows(&mut conn, &rows).unwrap();
+        rows[0].outlier = true;
+        insert_rows(&mut conn, &rows).unwrap();
+
+        assert_eq!(1, count(&conn, "machines"));
+        assert_eq!(1, count(&conn, "apps"));
+        assert_eq!(2, count(&conn, "datasets"));
+        assert_eq!(2, count(&conn, "runs"));
+        assert_eq!(3, count(&conn, "replicates"));
+        let outliers: i64 = conn
+            .query_row("SELECT SUM(outlier) FROM replicates", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(1, outliers);
+    }
+
+    #[test]
+    fn test_write_rows_from_two_blocks() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        // Both headers are matched to AMAS, and a log may repeat a header.
+        let rows = [
+            ("Benchmarking AMAS", 3, 1.2, 1),
+            ("Benchmarking AMAS", 3, 1.3, 2),
+            ("Benchmarking AMAS Check Align", 20, 2.4, 1),
+            ("Benchmarking AMAS Check Align", 20, 2.5, 2),
+            ("Benchmarking AMAS", 40, 1.4, 1),
+            ("Benchmarking AMAS", 40, 1.5, 2),
+        ]
+        .map(|(bench, line, secs, i)| {
+            RowBuilder::new("AMAS", "Wu", secs)
+                .version("v1.0")
+                .bench(bench, line)
+                .replicate(i)
+                .build()
+        });
+        insert_rows(&mut conn, &rows).unwrap();
+
+        assert_eq!(1, count(&conn, "apps"));
+        assert_eq!(3, count(&conn, "runs"));
+        assert_eq!(6, count(&conn, "replicates"));
+    }
+
+    #[test]
+    fn test_machine_key() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let rows = [("SEGUL CLI", "Linux"), ("SEGUL GUI", "Linux (GUI)")]
+            .map(|(app, os)| RowBuilder::new(app, "Wu", 2.6).os(os).replicate(1).build());
+        insert_rows(&mut conn, &rows).unwrap();
+        insert_rows(&mut conn, &rows).unwrap();
+
+        assert_eq!(1, count(&conn, "machines"));
+        assert_eq!(2, count(&conn, "runs"));
+    }
+
+    #[test]
+    fn test_write_rows_without_warmup() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let rows = [3.89, 3.62, 3.55]
+            .iter()
+            .enumerate()
+            .map(|(i, &secs)| {
+                RowBuilder::new("SEGUL CLI", "Oliveros", secs)
+                    .version("v0.18.1")
+                    .replicate(i + 1)
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        insert_rows(&mut conn, &rows).unwrap();
+        // Parsed again with the first replicate discarded.
+        insert_rows(&mut conn, &rows[1..]).unwrap();
+
+        let mut stmt = conn
+            .prepare("SELECT replicate, execution_time_secs FROM replicates ORDER BY replicate")
+            .unwrap();
+        let replicates: Vec<(i64, f64)> = stmt
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(vec![(2, 3.62), (3, 3.55)], replicates);
+    }
+}
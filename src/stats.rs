@@ -0,0 +1,439 @@
+//! Descriptive statistics and the distributions used by the reports.
+//!
+//! Values are slices of replicate measurements. Functions that need
+//! more than one value return `None` instead of NaN.
+
+pub(crate) fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().sum::<f64>() / values.len() as f64)
+}
+
+pub(crate) fn median(values: &[f64]) -> Option<f64> {
+    quantile(values, 0.5)
+}
+
+/// Linear interpolation between the closest ranks (R type 7).
+pub(crate) fn quantile(values: &[f64], q: f64) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let sorted = sorted(values);
+    let pos = q * (sorted.len() - 1) as f64;
+    let lower = pos.floor() as usize;
+    let upper = pos.ceil() as usize;
+    Some(sorted[lower] + (sorted[upper] - sorted[lower]) * (pos - lower as f64))
+}
+
+/// Sample variance with n - 1 degrees of freedom.
+pub(crate) fn variance(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let mean = mean(values)?;
+    let ss = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>();
+    Some(ss / (values.len() - 1) as f64)
+}
+
+pub(crate) fn std_dev(values: &[f64]) -> Option<f64> {
+    variance(values).map(f64::sqrt)
+}
+
+pub(crate) fn sorted(values: &[f64]) -> Vec<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    sorted
+}
+
+/// Two-sided confidence interval of the mean with the t distribution.
+pub(crate) fn mean_ci(values: &[f64], level: f64) -> Option<(f64, f64)> {
+    let n = values.len() as f64;
+    let mean = mean(values)?;
+    let se = std_dev(values)? / n.sqrt();
+    let t = t_quantile(1.0 - (1.0 - level) / 2.0, n - 1.0);
+    Some((mean - t * se, mean + t * se))
+}
+
+/// Least-squares line with its coefficient of determination.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinearFit {
+    pub slope: f64,
+    pub intercept: f64,
+    /// Empty for two points, which always fit exactly.
+    pub r_squared: Option<f64>,
+    /// Number of points in the fit.
+    pub n: usize,
+}
+
+/// Ordinary least squares of `y` on `x`. Needs at least two
+/// distinct `x` values, and three points for the R².
+pub(crate) fn linear_regression(x: &[f64], y: &[f64]) -> Option<LinearFit> {
+    if x.len() != y.len() {
+        return None;
+    }
+    let mean_x = mean(x)?;
+    let mean_y = mean(y)?;
+    let sxx: f64 = x.iter().map(|v| (v - mean_x).powi(2)).sum();
+    let syy: f64 = y.iter().map(|v| (v - mean_y).powi(2)).sum();
+    let sxy: f64 = x
+        .iter()
+        .zip(y)
+        .map(|(a, b)| (a - mean_x) * (b - mean_y))
+        .sum();
+    if sxx <= 0.0 {
+        return None;
+    }
+    let slope = sxy / sxx;
+    // A flat y is fully explained by a flat line.
+    let r_squared = match x.len() {
+        0..=2 => None,
+        _ if syy > 0.0 => Some(sxy * sxy / (sxx * syy)),
+        _ => Some(1.0),
+    };
+    Some(LinearFit {
+        slope,
+        intercept: mean_y - slope * mean_x,
+        r_squared,
+        n: x.len(),
+    })
+}
+
+/// Statistic and two-sided p-value of a hypothesis test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TestResult {
+    pub statistic: f64,
+    pub p_value: f64,
+}
+
+/// Mann-Whitney U test with the normal approximation,
+/// corrected for ties and continuity. The statistic is U of `a`.
+pub(crate) fn mann_whitney_u(a: &[f64], b: &[f64]) -> Option<TestResult> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    let pooled: Vec<f64> = a.iter().chain(b.iter()).copied().collect();
+    let (ranks, ties) = rank(&pooled);
+    let rank_sum: f64 = ranks[..a.len()].iter().sum();
+    let u = rank_sum - n1 * (n1 + 1.0) / 2.0;
+    let n = n1 + n2;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        // Every value is tied.
+        return Some(TestResult {
+            statistic: u,
+            p_value: 1.0,
+        });
+    }
+    let diff = u - n1 * n2 / 2.0;
+    let z = (diff.abs() - 0.5).max(0.0) / variance.sqrt();
+    Some(TestResult {
+        statistic: u,
+        p_value: (2.0 * (1.0 - normal_cdf(z))).min(1.0),
+    })
+}
+
+/// Welch's t test for samples with unequal variances.
+pub(crate) fn welch_t(a: &[f64], b: &[f64]) -> Option<TestResult> {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    let (v1, v2) = (variance(a)? / n1, variance(b)? / n2);
+    let se = (v1 + v2).sqrt();
+    if se == 0.0 {
+        return None;
+    }
+    let t = (mean(a)? - mean(b)?) / se;
+    let df = (v1 + v2).powi(2) / (v1.powi(2) / (n1 - 1.0) + v2.powi(2) / (n2 - 1.0));
+    Some(TestResult {
+        statistic: t,
+        p_value: 2.0 * (1.0 - t_cdf(t.abs(), df)),
+    })
+}
+
+/// Holm-Bonferroni adjusted p-values, in the order of the input.
+pub(crate) fn holm(p_values: &[Option<f64>]) -> Vec<Option<f64>> {
+    let mut order: Vec<usize> = (0..p_values.len())
+        .filter(|&i| p_values[i].is_some())
+        .collect();
+    order.sort_by(|&i, &j| p_values[i].unwrap().total_cmp(&p_values[j].unwrap()));
+    let m = order.len();
+    let mut adjusted = vec![None; p_values.len()];
+    let mut running_max: f64 = 0.0;
+    for (k, &i) in order.iter().enumerate() {
+        let p = (p_values[i].unwrap() * (m - k) as f64).min(1.0);
+        running_max = running_max.max(p);
+        adjusted[i] = Some(running_max);
+    }
+    adjusted
+}
+
+// Average ranks, starting from 1, and the tie correction sum(t^3 - t).
+fn rank(values: &[f64]) -> (Vec<f64>, f64) {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&i, &j| values[i].total_cmp(&values[j]));
+    let mut ranks = vec![0.0; values.len()];
+    let mut ties = 0.0;
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let avg_rank = (start + end + 1) as f64 / 2.0;
+        for &i in &order[start..end] {
+            ranks[i] = avg_rank;
+        }
+        let t = (end - start) as f64;
+        ties += t.powi(3) - t;
+        start = end;
+    }
+    (ranks, ties)
+}
+
+/// Percentile bootstrap confidence interval of `statistic(a, b)`,
+/// resampling both samples with replacement.
+pub(crate) fn bootstrap_ci<F>(
+    a: &[f64],
+    b: &[f64],
+    statistic: F,
+    level: f64,
+    rng: &mut SplitMix64,
+) -> Option<(f64, f64)>
+where
+    F: Fn(&[f64], &[f64]) -> Option<f64>,
+{
+    const ITERATIONS: usize = 2000;
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let resample = |values: &[f64], rng: &mut SplitMix64| -> Vec<f64> {
+        (0..values.len())
+            .map(|_| values[rng.below(values.len())])
+            .collect()
+    };
+    let estimates: Vec<f64> = (0..ITERATIONS)
+        .filter_map(|_| statistic(&resample(a, rng), &resample(b, rng)))
+        .filter(|e| e.is_finite())
+        .collect();
+    let alpha = (1.0 - level) / 2.0;
+    Some((
+        quantile(&estimates, alpha)?,
+        quantile(&estimates, 1.0 - alpha)?,
+    ))
+}
+
+/// Small seeded generator so the bootstrap results are reproducible.
+pub(crate) struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform integer in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// Cumulative distribution function of Student's t distribution.
+pub(crate) fn t_cdf(t: f64, df: f64) -> f64 {
+    let x = df / (df + t * t);
+    let tail = 0.5 * incomplete_beta(df / 2.0, 0.5, x);
+    if t > 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+/// Inverse of [`t_cdf`], found by bisection.
+pub(crate) fn t_quantile(p: f64, df: f64) -> f64 {
+    let (mut lower, mut upper) = (-1e3, 1e3);
+    for _ in 0..200 {
+        let mid = (lower + upper) / 2.0;
+        if t_cdf(mid, df) < p {
+            lower = mid;
+        } else {
+            upper = mid;
+        }
+    }
+    (lower + upper) / 2.0
+}
+
+/// Cumulative distribution function of the standard normal distribution.
+pub(crate) fn normal_cdf(z: f64) -> f64 {
+    0.5 * erfc(-z / std::f64::consts::SQRT_2)
+}
+
+// Complementary error function with a Chebyshev approximation,
+// accurate to 1.2e-7 (Numerical Recipes, erfcc).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807
+                            + t * (-1.13520398
+                                + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    let ans = t * poly.exp();
+    if x >= 0.0 {
+        ans
+    } else {
+        2.0 - ans
+    }
+}
+
+// Regularized incomplete beta function I_x(a, b).
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // The continued fraction converges quickly below the mean.
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+// Lentz's method for the continued fraction of the incomplete beta.
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-30;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..300 {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+        let num = m * (b - m) * x / ((a + m2 - 1.0) * (a + m2));
+        d = 1.0 + num * d;
+        d = if d.abs() < TINY { TINY } else { d };
+        c = 1.0 + num / c;
+        c = if c.abs() < TINY { TINY } else { c };
+        d = 1.0 / d;
+        h *= d * c;
+        let num = -(a + m) * (a + b + m) * x / ((a + m2) * (a + m2 + 1.0));
+        d = 1.0 + num * d;
+        d = if d.abs() < TINY { TINY } else { d };
+        c = 1.0 + num / c;
+        c = if c.abs() < TINY { TINY } else { c };
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-12 {
+            break;
+        }
+    }
+    h
+}
+
+// Lanczos approximation of ln Γ(x).
+fn ln_gamma(x: f64) -> f64 {
+    const COEF: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut ser = 1.000000000190015;
+    for (i, c) in COEF.iter().enumerate() {
+        ser += c / (x + 1.0 + i as f64);
+    }
+    -tmp + (2.5066282746310005 * ser / x).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_descriptive_stats() {
+        let values = [2.32, 2.33, 2.34, 2.26, 2.33];
+        assert!((mean(&values).unwrap() - 2.316).abs() < 1e-9);
+        assert_eq!(Some(2.33), median(&values));
+        assert!((std_dev(&values).unwrap() - 0.032093613).abs() < 1e-6);
+        assert_eq!(Some(1.75), quantile(&[1.0, 2.0, 3.0, 4.0], 0.25));
+        assert_eq!(None, std_dev(&[1.0]));
+    }
+
+    #[test]
+    fn test_linear_regression() {
+        let fit = linear_regression(&[1.0, 2.0, 3.0, 4.0], &[2.1, 3.9, 6.2, 7.8]).unwrap();
+        assert!((fit.slope - 1.94).abs() < 1e-9);
+        assert!((fit.intercept - 0.15).abs() < 1e-9);
+        assert!((fit.r_squared.unwrap() - 0.995661).abs() < 1e-6);
+        assert_eq!(4, fit.n);
+        assert_eq!(None, linear_regression(&[1.0, 1.0], &[2.0, 3.0]));
+        let fit = linear_regression(&[1.0, 2.0], &[2.0, 3.0]).unwrap();
+        assert_eq!((1.0, None, 2), (fit.slope, fit.r_squared, fit.n));
+    }
+
+    #[test]
+    fn test_distributions() {
+        // Two-sided 95% critical values.
+        assert!((t_quantile(0.975, 9.0) - 2.262157).abs() < 1e-4);
+        assert!((t_quantile(0.975, 1.0) - 12.7062).abs() < 1e-3);
+        assert!((t_cdf(0.0, 5.0) - 0.5).abs() < 1e-9);
+        assert!((normal_cdf(1.96) - 0.975).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_hypothesis_tests() {
+        // Every value of `a` is below `b`, with one tie in `a`.
+        let a = [2.32, 2.33, 2.34, 2.26, 2.33];
+        let b = [3.89, 3.64, 3.69, 3.65, 3.63];
+        let mw = mann_whitney_u(&a, &b).unwrap();
+        assert_eq!(0.0, mw.statistic);
+        assert!((mw.p_value - 0.01193).abs() < 1e-4);
+        let welch = welch_t(&a, &b).unwrap();
+        assert!((welch.statistic - -27.321).abs() < 1e-2);
+        assert!(welch.p_value < 1e-4);
+    }
+
+    #[test]
+    fn test_holm() {
+        let adjusted = holm(&[Some(0.01), None, Some(0.04), Some(0.03)]);
+        assert_eq!(Some(0.03), adjusted[0]);
+        assert_eq!(None, adjusted[1]);
+        assert!((adjusted[2].unwrap() - 0.06).abs() < 1e-12);
+        assert!((adjusted[3].unwrap() - 0.06).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_bootstrap_ci() {
+        let a = [10.0, 11.0, 9.0, 10.5, 9.5];
+        let b = [2.0, 2.2, 1.8, 2.1, 1.9];
+        let ratio = |a: &[f64], b: &[f64]| Some(median(a)? / median(b)?);
+        let mut rng = SplitMix64::new(42);
+        let (lower, upper) = bootstrap_ci(&a, &b, ratio, 0.95, &mut rng).unwrap();
+        assert!(lower < 5.0 && upper > 5.0);
+        assert!(lower > 4.0 && upper < 6.5);
+    }
+}
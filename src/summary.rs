@@ -0,0 +1,223 @@
+//! Summary statistics of the replicates.
+//!
+//! Rows are grouped by app, version, dataset, analysis, and machine.
+//! Groups are kept in the order they first appear in the input.
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io::Write;
+
+use crate::row::{format_optional, BenchRow};
+use crate::stats;
+
+const CI_LEVEL: f64 = 0.95;
+
+const METRICS: [&str; 3] = ["Execution_time_secs", "RAM_usage_Mb", "Percent_CPU_usage"];
+
+/// Descriptive statistics of a metric. The standard deviation,
+/// coefficient of variation, and confidence interval need at least
+/// two values.
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    pub n: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    pub sd: Option<f64>,
+    pub cv: Option<f64>,
+    /// 95% confidence interval of the mean.
+    pub ci: Option<(f64, f64)>,
+}
+
+impl Summary {
+    pub fn from_values(values: &[f64]) -> Option<Self> {
+        let mean = stats::mean(values)?;
+        let sd = stats::std_dev(values);
+        Some(Self {
+            n: values.len(),
+            mean,
+            median: stats::median(values)?,
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            sd,
+            cv: sd.map(|sd| sd / mean),
+            ci: stats::mean_ci(values, CI_LEVEL),
+        })
+    }
+
+    fn fields(summary: Option<&Summary>) -> Vec<String> {
+        match summary {
+            Some(s) => vec![
+                s.mean.to_string(),
+                s.median.to_string(),
+                s.min.to_string(),
+                s.max.to_string(),
+                format_optional(s.sd),
+                format_optional(s.cv),
+                format_optional(s.ci.map(|ci| ci.0)),
+                format_optional(s.ci.map(|ci| ci.1)),
+            ],
+            None => vec![String::new(); 8],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupSummary {
+    pub app: String,
+    pub version: String,
+    pub dataset: String,
+    pub analysis: String,
+    pub os: String,
+    pub cpu: String,
+    pub replicates: usize,
+    pub exec_time: Option<Summary>,
+    pub ram_usage: Option<Summary>,
+    pub cpu_usage: Option<Summary>,
+}
+
+impl GroupSummary {
+    fn from_rows(rows: &[&BenchRow]) -> Self {
+        let first = rows[0];
+        let values = |f: &dyn Fn(&BenchRow) -> Option<f64>| {
+            rows.iter().filter_map(|r| f(r)).collect::<Vec<f64>>()
+        };
+        Self {
+            app: first.app.clone(),
+            version: first.version.clone(),
+            dataset: first.dataset.clone(),
+            analysis: first.analysis.clone(),
+            os: first.os.clone(),
+            cpu: first.cpu.clone(),
+            replicates: rows.len(),
+            exec_time: Summary::from_values(&values(&|r| Some(r.exec_time_secs))),
+            ram_usage: Summary::from_values(&values(&|r| r.ram_usage_mb.map(f64::from))),
+            cpu_usage: Summary::from_values(&values(&|r| r.cpu_usage)),
+        }
+    }
+}
+
+/// Groups the rows and summarizes each group.
+pub fn summarize(rows: &[BenchRow], exclude_outliers: bool) -> Vec<GroupSummary> {
+    group_rows(rows, exclude_outliers)
+        .iter()
+        .map(|group| GroupSummary::from_rows(group))
+        .collect()
+}
+
+/// Groups the rows by app, version, dataset, analysis, and machine.
+/// Warm-up replicates are left out, and so are the outliers if asked.
+pub(crate) fn group_rows(rows: &[BenchRow], exclude_outliers: bool) -> Vec<Vec<&BenchRow>> {
+    group_rows_by(rows, exclude_outliers, |row| {
+        (
+            row.app.as_str(),
+            row.version.as_str(),
+            row.dataset.as_str(),
+            row.analysis.as_str(),
+            row.machine(),
+        )
+    })
+}
+
+/// Same as [`group_rows`], but grouped by `key`.
+pub(crate) fn group_rows_by<'a, K: Hash + Eq>(
+    rows: &'a [BenchRow],
+    exclude_outliers: bool,
+    key: impl Fn(&'a BenchRow) -> K,
+) -> Vec<Vec<&'a BenchRow>> {
+    let mut index: HashMap<K, usize> = HashMap::new();
+    let mut groups: Vec<Vec<&BenchRow>> = Vec::new();
+    let rows = rows
+        .iter()
+        .filter(|r| !(r.warmup || exclude_outliers && r.outlier));
+    for row in rows {
+        let key = key(row);
+        match index.get(&key) {
+            Some(&i) => groups[i].push(row),
+            None => {
+                index.insert(key, groups.len());
+                groups.push(vec![row]);
+            }
+        }
+    }
+    groups
+}
+
+pub(crate) fn write_summary<W: Write>(
+    writer: &mut W,
+    summaries: &[GroupSummary],
+) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    let mut header: Vec<String> = [
+        "Apps",
+        "Version",
+        "Datasets",
+        "Analyses",
+        "OS_name",
+        "CPU",
+        "Replicates",
+    ]
+    .iter()
+    .map(|h| h.to_string())
+    .collect();
+    for metric in METRICS {
+        for stat in [
+            "mean", "median", "min", "max", "sd", "cv", "ci_lower", "ci_upper",
+        ] {
+            header.push(format!("{}_{}", metric, stat));
+        }
+    }
+    writer.write_record(&header)?;
+    for s in summaries {
+        let mut record = vec![
+            s.app.clone(),
+            s.version.clone(),
+            s.dataset.clone(),
+            s.analysis.clone(),
+            s.os.clone(),
+            s.cpu.clone(),
+            s.replicates.to_string(),
+        ];
+        record.extend(Summary::fields(s.exec_time.as_ref()));
+        record.extend(Summary::fields(s.ram_usage.as_ref()));
+        record.extend(Summary::fields(s.cpu_usage.as_ref()));
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::row::RowBuilder;
+
+    #[test]
+    fn test_summarize() {
+        let rows = [
+            RowBuilder::new("SEGUL CLI", "Wu", 8.0).warmup().build(),
+            RowBuilder::new("SEGUL CLI", "Wu", 2.0).build(),
+            RowBuilder::new("AMAS", "Wu", 10.0).build(),
+            RowBuilder::new("SEGUL CLI", "Wu", 4.0).build(),
+        ];
+        let summaries = summarize(&rows, false);
+        assert_eq!(2, summaries.len());
+        assert_eq!("SEGUL CLI", summaries[0].app);
+        let exec_time = summaries[0].exec_time.as_ref().unwrap();
+        assert_eq!(2, exec_time.n);
+        assert_eq!(3.0, exec_time.mean);
+        assert_eq!(Some(2.0_f64.sqrt()), exec_time.sd);
+        assert!(summaries[0].cpu_usage.is_none());
+        assert!(summaries[1].exec_time.as_ref().unwrap().ci.is_none());
+    }
+
+    #[test]
+    fn test_summarize_without_outliers() {
+        let rows = [
+            RowBuilder::new("SEGUL CLI", "Wu", 2.0).build(),
+            RowBuilder::new("SEGUL CLI", "Wu", 8.0).outlier().build(),
+        ];
+        assert_eq!(2, summarize(&rows, false)[0].replicates);
+        assert_eq!(1, summarize(&rows, true)[0].replicates);
+    }
+}
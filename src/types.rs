@@ -1,6 +1,14 @@
-#[derive(Debug)]
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Deserialize;
+
+#[derive(Debug, Default)]
 pub struct Benchmark {
     pub bench: String,
+    /// Line of the `Benchmarking` header in the log, or the position
+    /// of the command in a hyperfine export, from 1.
+    pub line: usize,
     pub dataset: Vec<Dataset>,
 }
 
@@ -8,31 +16,96 @@ impl Benchmark {
     pub fn new() -> Self {
         Self {
             bench: String::new(),
+            line: 0,
             dataset: Vec::new(),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Records {
-    pub cpu: String,
-    pub os: String,
+    pub machine: MachineInfo,
     pub segul_version: String,
+    /// Analysis from the `Analysis:` line. Empty if absent.
+    pub analysis: String,
     pub benchmark: Benchmark,
 }
 
 impl Records {
     pub fn new() -> Self {
         Self {
-            cpu: String::new(),
-            os: String::new(),
+            machine: MachineInfo::new(),
             segul_version: String::new(),
+            analysis: String::new(),
             benchmark: Benchmark::new(),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// Machine the benchmark ran on.
+#[derive(Debug, Clone, Default)]
+pub struct MachineInfo {
+    pub host: String,
+    pub cpu: String,
+    pub os: String,
+    pub kernel: String,
+    pub total_ram_kb: Option<u64>,
+    /// Physical cores. Overrides the lscpu counts.
+    pub cores: Option<usize>,
+    /// Logical CPUs. Overrides the lscpu counts.
+    pub threads: Option<usize>,
+    /// Every `key: value` line of the lscpu output.
+    pub lscpu: BTreeMap<String, String>,
+}
+
+impl MachineInfo {
+    pub fn new() -> Self {
+        Self {
+            host: String::new(),
+            cpu: String::new(),
+            os: String::new(),
+            kernel: String::new(),
+            total_ram_kb: None,
+            cores: None,
+            threads: None,
+            lscpu: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the lscpu value of `key`, e.g. `Thread(s) per core`.
+    pub fn lscpu_value(&self, key: &str) -> Option<&str> {
+        self.lscpu.get(key).map(|v| v.as_str())
+    }
+
+    fn lscpu_count(&self, key: &str) -> Option<usize> {
+        self.lscpu_value(key).and_then(|v| v.parse().ok())
+    }
+
+    /// Cores per socket times sockets.
+    pub fn physical_cores(&self) -> Option<usize> {
+        self.cores.or_else(|| {
+            let sockets = self.lscpu_count("Socket(s)").unwrap_or(1);
+            self.lscpu_count("Core(s) per socket")
+                .map(|cores| cores * sockets)
+        })
+    }
+
+    /// `CPU(s)`, or physical cores times threads per core.
+    pub fn logical_cpus(&self) -> Option<usize> {
+        self.threads
+            .or_else(|| self.lscpu_count("CPU(s)"))
+            .or_else(|| {
+                let threads = self.lscpu_count("Thread(s) per core").unwrap_or(1);
+                self.physical_cores().map(|cores| cores * threads)
+            })
+    }
+
+    pub fn numa_nodes(&self) -> Option<usize> {
+        self.lscpu_count("NUMA node(s)")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Dataset {
     pub name: String,
     pub result: Vec<BenchmarkResult>,
@@ -56,11 +129,24 @@ impl Dataset {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct BenchmarkResult {
     pub exec_time: String,
     pub mem_usage: String,
     pub cpu_usage: String,
+    // Only available in the GNU verbose or BSD/macOS output.
+    pub user_time: Option<f64>,
+    pub system_time: Option<f64>,
+    pub major_page_faults: Option<u64>,
+    pub minor_page_faults: Option<u64>,
+    pub voluntary_switches: Option<u64>,
+    pub involuntary_switches: Option<u64>,
+    pub fs_inputs: Option<u64>,
+    pub fs_outputs: Option<u64>,
+    pub exit_status: Option<i32>,
+    // Only available in the BSD/macOS output.
+    pub instructions_retired: Option<u64>,
+    pub peak_memory_kb: Option<u64>,
 }
 
 impl BenchmarkResult {
@@ -69,13 +155,28 @@ impl BenchmarkResult {
             exec_time: String::new(),
             mem_usage: String::new(),
             cpu_usage: String::new(),
+            user_time: None,
+            system_time: None,
+            major_page_faults: None,
+            minor_page_faults: None,
+            voluntary_switches: None,
+            involuntary_switches: None,
+            fs_inputs: None,
+            fs_outputs: None,
+            exit_status: None,
+            instructions_retired: None,
+            peak_memory_kb: None,
         }
     }
 }
 
+#[derive(Debug, Default)]
 pub struct Apps {
     pub name: String,
     pub version: String,
+    pub app_type: AppType,
+    /// Overrides the machine OS, e.g. for GUI apps.
+    pub os: Option<String>,
 }
 
 impl Apps {
@@ -83,10 +184,34 @@ impl Apps {
         Self {
             name: String::new(),
             version: String::new(),
+            app_type: AppType::Cli,
+            os: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum AppType {
+    #[default]
+    #[serde(rename = "CLI")]
+    Cli,
+    #[serde(rename = "GUI")]
+    Gui,
+    #[serde(rename = "API")]
+    Api,
+}
+
+impl fmt::Display for AppType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppType::Cli => write!(f, "CLI"),
+            AppType::Gui => write!(f, "GUI"),
+            AppType::Api => write!(f, "API"),
         }
     }
 }
 
+#[derive(Debug, Clone, Default)]
 pub struct Pubs {
     pub name: String,
     pub ntax: usize,
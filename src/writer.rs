@@ -0,0 +1,395 @@
+//! Writers of the output rows.
+//!
+//! CSV and TSV fields go through the CSV writer, so values with the
+//! delimiter, quotes, or line breaks are quoted as in RFC 4180.
+//! JSON is nested as machine, benchmark, dataset, and replicates,
+//! and NDJSON has one flat object per replicate. Both use the CSV
+//! column names as keys, with the numbers and flags typed.
+//! Parquet and Arrow IPC are written in batches of rows.
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+
+use crate::columnar::{ColumnarWriter, BATCH_SIZE};
+use crate::error::BenchParseError;
+use crate::row::{BenchRow, OutputRow, HEADER};
+
+// Keys of each level of the nested JSON. The rest are replicate keys.
+const MACHINE_KEYS: [&str; 7] = [
+    "OS_name",
+    "CPU",
+    "Platform",
+    "Core_counts",
+    "Thread_counts",
+    "Total_RAM_kb",
+    "Kernel_version",
+];
+const BENCHMARK_KEYS: [&str; 6] = [
+    "Apps",
+    "Version",
+    "App_type",
+    "Analyses",
+    "Benchmark_dates",
+    "Latest_bench",
+];
+const DATASET_KEYS: [&str; 8] = [
+    "Datasets",
+    "Pubs",
+    "NTAX",
+    "Character_counts",
+    "Alignment_counts",
+    "Site_counts",
+    "Datatype",
+    "Replicates",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Csv,
+    /// Tab-separated values.
+    Tsv,
+    /// Nested JSON array of machines.
+    Json,
+    /// Newline-delimited JSON, one replicate per line.
+    Ndjson,
+    Parquet,
+    /// Arrow IPC file.
+    Arrow,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Csv => "csv",
+            OutputFormat::Tsv => "tsv",
+            OutputFormat::Json => "json",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::Arrow => "arrow",
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "parquet" => Ok(OutputFormat::Parquet),
+            "arrow" => Ok(OutputFormat::Arrow),
+            _ => Err(format!("Unknown output format: {}", s)),
+        }
+    }
+}
+
+pub(crate) enum RowWriter<W: Write + Send> {
+    Csv(Box<csv::Writer<W>>),
+    Ndjson(W),
+    /// The nested output is written once every row is in.
+    Json(W, Vec<Map<String, Value>>),
+    /// Parquet or Arrow IPC, written in batches of up to `BATCH_SIZE` rows.
+    Columnar(Box<ColumnarWriter<W>>),
+}
+
+impl RowWriter<BufWriter<File>> {
+    pub(crate) fn create(path: &Path, format: OutputFormat) -> Result<Self, BenchParseError> {
+        Self::new(BufWriter::new(File::create(path)?), format)
+    }
+}
+
+impl<W: Write + Send> RowWriter<W> {
+    /// Writes the CSV header, so that an output without rows still has it.
+    pub(crate) fn new(output: W, format: OutputFormat) -> Result<Self, BenchParseError> {
+        let delimiter = match format {
+            OutputFormat::Csv => b',',
+            OutputFormat::Tsv => b'\t',
+            OutputFormat::Json => return Ok(RowWriter::Json(output, Vec::new())),
+            OutputFormat::Ndjson => return Ok(RowWriter::Ndjson(output)),
+            OutputFormat::Parquet => return Ok(Self::columnar(ColumnarWriter::parquet(output)?)),
+            OutputFormat::Arrow => return Ok(Self::columnar(ColumnarWriter::arrow(output)?)),
+        };
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .from_writer(output);
+        writer.write_record(HEADER.iter())?;
+        Ok(RowWriter::Csv(Box::new(writer)))
+    }
+
+    fn columnar(writer: ColumnarWriter<W>) -> Self {
+        RowWriter::Columnar(Box::new(writer))
+    }
+
+    /// Writes the rows in order. Parquet and Arrow IPC get them
+    /// as record batches straight from the slice.
+    pub(crate) fn write_rows(&mut self, rows: &[&BenchRow]) -> Result<(), BenchParseError> {
+        if let RowWriter::Columnar(writer) = self {
+            for chunk in rows.chunks(BATCH_SIZE) {
+                writer.write(chunk)?;
+            }
+            return Ok(());
+        }
+        for row in rows {
+            self.write(row)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, row: &BenchRow) -> Result<(), BenchParseError> {
+        match self {
+            RowWriter::Csv(writer) => {
+                let record = OutputRow::from(row).to_record().map_err(io::Error::from)?;
+                writer.write_record(&record)?;
+            }
+            RowWriter::Ndjson(writer) => {
+                serde_json::to_writer(&mut *writer, &OutputRow::from(row))
+                    .map_err(io::Error::from)?;
+                writeln!(writer)?;
+            }
+            RowWriter::Json(_, rows) => {
+                rows.push(OutputRow::from(row).to_map().map_err(io::Error::from)?)
+            }
+            RowWriter::Columnar(writer) => writer.write(&[row])?,
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(self) -> Result<(), BenchParseError> {
+        match self {
+            RowWriter::Csv(mut writer) => writer.flush()?,
+            RowWriter::Ndjson(mut writer) => writer.flush()?,
+            RowWriter::Json(mut writer, rows) => {
+                serde_json::to_writer_pretty(&mut writer, &nest(rows)).map_err(io::Error::from)?;
+                writeln!(writer)?;
+                writer.flush()?;
+            }
+            RowWriter::Columnar(writer) => writer.finish()?,
+        }
+        Ok(())
+    }
+}
+
+// Node of the nested output, with its fields and children.
+#[derive(Default)]
+struct Node {
+    fields: Map<String, Value>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    // Child with the given fields, created if missing.
+    fn child(&mut self, fields: Map<String, Value>) -> &mut Node {
+        let i = match self.children.iter().position(|c| c.fields == fields) {
+            Some(i) => i,
+            None => {
+                self.children.push(Node {
+                    fields,
+                    children: Vec::new(),
+                });
+                self.children.len() - 1
+            }
+        };
+        &mut self.children[i]
+    }
+
+    // `names` are the keys of the children at each level below.
+    fn into_value(self, names: &[&str]) -> Value {
+        let children: Vec<Value> = self
+            .children
+            .into_iter()
+            .map(|c| c.into_value(names.get(1..).unwrap_or_default()))
+            .collect();
+        match names.first() {
+            Some(name) => {
+                let mut fields = self.fields;
+                fields.insert(name.to_string(), Value::Array(children));
+                Value::Object(fields)
+            }
+            None => Value::Object(self.fields),
+        }
+    }
+}
+
+// Groups the rows by machine, benchmark, and dataset,
+// in the order they first appear.
+fn nest(rows: Vec<Map<String, Value>>) -> Value {
+    let mut root = Node::default();
+    for mut row in rows {
+        let machine = take(&mut row, &MACHINE_KEYS);
+        let benchmark = take(&mut row, &BENCHMARK_KEYS);
+        let dataset = take(&mut row, &DATASET_KEYS);
+        root.child(machine)
+            .child(benchmark)
+            .child(dataset)
+            .children
+            .push(Node {
+                fields: row,
+                children: Vec::new(),
+            });
+    }
+    match root.into_value(&["machines", "benchmarks", "datasets", "replicates"]) {
+        Value::Object(mut root) => root.remove("machines").unwrap_or_default(),
+        _ => unreachable!("Nodes are objects"),
+    }
+}
+
+fn take(row: &mut Map<String, Value>, keys: &[&str]) -> Map<String, Value> {
+    keys.iter()
+        .filter_map(|k| row.remove(*k).map(|v| (k.to_string(), v)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(rows: &[BenchRow], format: OutputFormat) -> String {
+        let mut output = Vec::new();
+        let mut writer = RowWriter::new(&mut output, format).unwrap();
+        writer.write_rows(&rows.iter().collect::<Vec<_>>()).unwrap();
+        writer.finish().unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_quoting() {
+        let mut row = BenchRow::new();
+        row.app = String::from("Phyluce, \"raw\"");
+        row.cpu = String::from("Intel(R) Core(TM) i5-4260U CPU @ 1.40GHz, 2 cores");
+        let output = write(&[row], OutputFormat::Csv);
+        let mut reader = csv::Reader::from_reader(output.as_bytes());
+        assert_eq!(HEADER.len(), reader.headers().unwrap().len());
+        let record = reader.records().next().unwrap().unwrap();
+        assert_eq!(HEADER.len(), record.len());
+        assert_eq!("Phyluce, \"raw\"", &record[0]);
+        assert_eq!(
+            "Intel(R) Core(TM) i5-4260U CPU @ 1.40GHz, 2 cores",
+            &record[13]
+        );
+        assert!(output.contains("\"Phyluce, \"\"raw\"\"\""));
+    }
+
+    #[test]
+    fn test_csv_row() {
+        let mut row = BenchRow::new();
+        row.app = String::from("SEGUL CLI");
+        row.version = String::from("v0.18.1");
+        row.pubs = String::from("Wu et al. 2018");
+        row.dataset = String::from("Wu et al. 2018 (257.1 MBases, AA)");
+        row.ntax = 37;
+        row.char_counts = 257_100_000;
+        row.aln_counts = 5162;
+        row.site_counts = 1_796_217;
+        row.datatype = String::from("AA");
+        row.analysis = String::from("Alignment Concatenation (NEXUS)");
+        row.platform = String::from("Desktop");
+        row.app_type = String::from("CLI");
+        row.os = String::from("Linux");
+        row.cpu = String::from("AMD Ryzen 9 3900X 12-Core Processor");
+        row.date = String::from("10/04/2022");
+        row.latest_bench = true;
+        row.exec_time = String::from("0:02.00");
+        row.ram_usage_kb = Some(353075);
+        row.cpu_usage = Some(142.0);
+        row.exec_time_secs = 2.0;
+        row.ram_usage_mb = Some(344.8);
+        row.replicates = 10;
+        row.cores = Some(12);
+        row.threads = Some(24);
+        row.kernel = String::from("5.19.13-1-default");
+        row.cpu_usage_normalized = Some(142.0 / 24.0);
+        let output = write(&[row], OutputFormat::Csv);
+        assert_eq!(
+            Some(
+                "SEGUL CLI,v0.18.1,Wu et al. 2018,\"Wu et al. 2018 (257.1 MBases, AA)\",\
+                37,257100000,5162,1796217,AA,Alignment Concatenation (NEXUS),Desktop,CLI,\
+                Linux,AMD Ryzen 9 3900X 12-Core Processor,10/04/2022,TRUE,0:02.00,353075,\
+                142,2,344.8,10,,,,,,,,,,,,12,24,,5.19.13-1-default,5.916666666666667,\
+                FALSE,FALSE,128.55,2581,1.3411123601437913,0.38744672607516467"
+            ),
+            output.lines().nth(1)
+        );
+    }
+
+    #[test]
+    fn test_header_matches_row() {
+        let output = write(&[], OutputFormat::Csv);
+        assert_eq!(Some(HEADER.join(",").as_str()), output.lines().next());
+        assert_eq!("Apps", HEADER[0]);
+        assert_eq!("Secs_per_1k_alignments", HEADER[HEADER.len() - 1]);
+        assert_eq!(44, HEADER.len());
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let mut row = BenchRow::new();
+        row.exec_time_secs = 2.0;
+        row.ram_usage_kb = Some(382304);
+        row.ram_usage_mb = Some(344.8);
+        row.warmup = true;
+        let output = write(&[row.clone(), row], OutputFormat::Ndjson);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(2, lines.len());
+        let value: Value = serde_json::from_str(lines[0]).unwrap();
+        let object = value.as_object().unwrap();
+        assert_eq!(HEADER.len(), object.len());
+        assert!(HEADER.iter().all(|h| object.contains_key(h)));
+        assert_eq!(Some(2.0), object["Execution_time_secs"].as_f64());
+        assert_eq!(Some(382304), object["RAM_usage_kb"].as_u64());
+        assert_eq!(Some(344.8), object["RAM_usage_Mb"].as_f64());
+        assert_eq!(Some(true), object["Warmup"].as_bool());
+    }
+
+    #[test]
+    fn test_nested_json() {
+        let row = |cpu: &str, dataset: &str| {
+            let mut row = BenchRow::new();
+            row.cpu = String::from(cpu);
+            row.dataset = String::from(dataset);
+            row
+        };
+        let rows = [
+            row("Apple M1", "Wu"),
+            row("Apple M1", "Wu"),
+            row("Apple M1", "Shen"),
+            row("AMD Ryzen 9 3900X", "Wu"),
+        ];
+        let value: Value = serde_json::from_str(&write(&rows, OutputFormat::Json)).unwrap();
+        let machines = value.as_array().unwrap();
+        assert_eq!(2, machines.len());
+        assert_eq!("Apple M1", machines[0]["CPU"]);
+        let benchmarks = machines[0]["benchmarks"].as_array().unwrap();
+        assert_eq!(1, benchmarks.len());
+        let datasets = benchmarks[0]["datasets"].as_array().unwrap();
+        assert_eq!(2, datasets.len());
+        assert_eq!("Wu", datasets[0]["Datasets"]);
+        let replicates = datasets[0]["replicates"].as_array().unwrap();
+        assert_eq!(2, replicates.len());
+        assert!(replicates[0].get("CPU").is_none());
+        assert!(replicates[0].get("Execution_time_secs").is_some());
+    }
+
+    #[test]
+    fn test_tsv() {
+        let output = write(&[BenchRow::new()], OutputFormat::Tsv);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(2, lines.len());
+        assert_eq!(HEADER.join("\t"), lines[0]);
+        assert_eq!(HEADER.len(), lines[1].split('\t').count());
+    }
+}